@@ -1,4 +1,4 @@
-use std::{path::Path, time::Duration};
+use std::time::Duration;
 
 use miniquad::{EventHandler, KeyCode, KeyMods, MouseButton, window};
 
@@ -6,17 +6,30 @@ use crate::{
 	assets::Assets,
 	audio::Audio,
 	cli::Cli,
-	emu::Emu,
+	emu::{Emu, Mode},
+	gamepad::Gamepads,
+	games::{GAMES, GameInfo},
 	input::Input,
 	math::{Color, Point},
-	native::NativeInstant,
+	movie::TapeMode,
+	native::{self, NativeInstant},
 	painter::{CanvasId, Painter, Sprite},
+	rom::{RomError, check_rom},
 	scene::Scene,
+	settings::{Settings, SettingsStore},
 	state::State,
 	tooltip::Tooltip,
 	util::Anim,
 };
 
+#[cfg(not(target_arch = "wasm32"))]
+use crate::{
+	debug_server::DebugServer,
+	games::scan_roms,
+	headless::{TraceFile, open_movie, open_trace},
+	rom::read_rom,
+};
+
 pub const CANVAS_WIDTH: f32 = 700.0;
 pub const CANVAS_HEIGHT: f32 = 700.0;
 
@@ -65,25 +78,64 @@ pub struct AppContext {
 	pub icons_anim: Anim,
 }
 
-#[allow(clippy::unused_io_amount)]
-#[cfg(not(target_arch = "wasm32"))]
-fn read_rom(path: impl AsRef<Path>) -> Option<[u8; Emu::PROGRAM_SIZE]> {
-	// TODO: notify users about ROM reading errors
-
-	use crate::emu::Emu;
-	use std::io::Read;
-
-	let Ok(mut file) = std::fs::File::open(path) else {
-		return None;
+/// Load the ROM specified in the CLI or the default one, and configure the emulator for it
+/// If the ROM can't be loaded, the emulator is left without a program and the error is returned
+pub fn load_rom(emu: &mut Emu, cli: &Cli) -> Option<RomError> {
+	load_rom_or(emu, cli, DEFAULT_ROM)
+}
+/// Same as [`load_rom`], but with another ROM loaded if none is specified in the CLI
+pub fn load_rom_or(emu: &mut Emu, cli: &Cli, default_rom: &[u8]) -> Option<RomError> {
+	#[cfg(target_arch = "wasm32")]
+	let rom: &[u8] = default_rom;
+
+	#[cfg(not(target_arch = "wasm32"))]
+	let ext_mode = cli
+		.rom_path
+		.as_ref()
+		.and_then(|path| path.extension())
+		.and_then(|ext| Mode::from_extension(&ext.to_string_lossy()));
+	#[cfg(target_arch = "wasm32")]
+	let ext_mode = None;
+
+	#[cfg(not(target_arch = "wasm32"))]
+	let rom_buf = match cli.rom_path.as_ref().map(read_rom).transpose() {
+		Ok(buf) => buf,
+		Err(e) => return Some(unload_rom(emu, e)),
 	};
-
-	let mut buf = [0_u8; Emu::PROGRAM_SIZE];
-	if file.read(&mut buf).is_ok() {
-		Some(buf)
-	} else {
-		None
+	#[cfg(not(target_arch = "wasm32"))]
+	let rom: &[u8] = rom_buf.as_deref().unwrap_or(default_rom);
+
+	let game = find_game(rom);
+	let quirks = cli.quirks.or(game.map(|g| g.quirks)).unwrap_or_default();
+	let mode = cli
+		.mode
+		.or(game.map(|g| g.mode))
+		.or(ext_mode)
+		.unwrap_or_default();
+
+	emu.quirks = quirks.quirks();
+	emu.mode = mode;
+
+	match check_rom(rom, mode) {
+		Ok(warning) => {
+			emu.load(rom);
+			warning
+		}
+		Err(e) => Some(unload_rom(emu, e)),
 	}
 }
+/// Leave the emulator without a program, so nothing runs instead of the requested ROM
+fn unload_rom(emu: &mut Emu, e: RomError) -> RomError {
+	emu.load(&[]);
+	e
+}
+
+/// Returns the built-in game with the same program data
+pub fn find_game(rom: &[u8]) -> Option<&'static GameInfo> {
+	GAMES.iter().find(|game| {
+		rom.starts_with(&game.bytes) && rom[game.bytes.len()..].iter().all(|b| *b == 0)
+	})
+}
 
 /// App
 pub struct App {
@@ -95,9 +147,19 @@ pub struct App {
 	pub canvas: CanvasId,
 	pub canvas_offset: Point,
 	pub canvas_scale: f32,
+
+	pub gamepads: Gamepads,
+	pub settings: SettingsStore,
+
+	#[cfg(not(target_arch = "wasm32"))]
+	pub debug_server: Option<DebugServer>,
+	#[cfg(not(target_arch = "wasm32"))]
+	pub trace: Option<TraceFile>,
 }
 impl App {
-	pub fn new(cli: Cli) -> Self {
+	pub fn new(mut cli: Cli) -> Self {
+		let settings = Settings::load();
+
 		let mut painter = Painter::new().unwrap_or_else(|e| {
 			panic!("failed to initialize painter: {e}");
 		});
@@ -113,21 +175,86 @@ impl App {
 			painter,
 			audio: Audio::new(cli.muted),
 		};
+		context.audio.set_muted(settings.muted);
 
 		let mut state = State::new();
 		if cfg!(debug_assertions) {
 			state.board.power = true;
 		}
 
-		// Load ROM
+		// Continue with the last played ROM, unless another one is specified
+		if cli.rom_path.is_none() {
+			cli.rom_path = settings.rom.clone().filter(|path| path.exists());
+		}
+		let last_game = settings
+			.game
+			.as_ref()
+			.and_then(|title| GAMES.iter().find(|game| game.title == title.as_str()));
+
+		state.rom_path = cli.rom_path.clone();
+		let default_rom = last_game.map_or(DEFAULT_ROM, |game| &game.bytes);
+		if let Some(e) = load_rom_or(&mut state.emu, &cli, default_rom) {
+			e.print(cli.rom_path.as_ref());
+			context.tooltip.set_error(e.to_string().as_bytes());
+		}
+		state.emu.set_speed(cli.speed.unwrap_or(settings.speed));
+		state.emu.ips = cli.ips.unwrap_or(Emu::DEFAULT_IPS);
+		state.board.side = cli.side.unwrap_or(settings.side);
+		if cli.start_powered {
+			state.board.power = true;
+		}
+		state.palette = cli.palette;
+		state.overheat = !cli.no_heat;
+		state
+			.emu
+			.set_seed(cli.seed.unwrap_or_else(|| native::now_millis() as u64));
+		state.emu.strict = cli.strict;
+		state.emu.memory_policy = cli.memory_policy;
+		state.emu.debugger.breakpoints = cli.breakpoints.clone();
+		state.emu.debugger.watchpoints = cli.watchpoints.clone();
+		#[cfg(not(target_arch = "wasm32"))]
+		let trace = open_trace(&cli, &mut state.emu);
+		#[cfg(not(target_arch = "wasm32"))]
+		{
+			state.tape = open_movie(&cli, &mut state.emu);
+		}
+		// Start the playback right away
+		if state
+			.tape
+			.as_ref()
+			.is_some_and(|tape| tape.mode == TapeMode::Play)
+		{
+			state.board.power = true;
+		}
+
+		#[cfg(not(target_arch = "wasm32"))]
+		let user_games = match &cli.roms_dir {
+			Some(dir) => scan_roms(dir).unwrap_or_else(|e| {
+				eprintln!(
+					"ERROR: failed to read ROMs directory {}: {e}",
+					dir.display()
+				);
+				context.tooltip.set_error(b"Failed to read ROMs directory");
+				Vec::new()
+			}),
+			None => Vec::new(),
+		};
 		#[cfg(target_arch = "wasm32")]
-		state.emu.load(DEFAULT_ROM);
+		let user_games = Vec::new();
 
 		#[cfg(not(target_arch = "wasm32"))]
-		match cli.rom_path.and_then(read_rom) {
-			Some(bytes) => state.emu.load(&bytes),
-			None => state.emu.load(DEFAULT_ROM),
-		}
+		let debug_server = cli
+			.debug_port
+			.and_then(|port| match DebugServer::bind(port) {
+				Ok(server) => {
+					println!("Debug server is listening on 127.0.0.1:{port}");
+					Some(server)
+				}
+				Err(e) => {
+					eprintln!("ERROR: failed to start the debug server on port {port}: {e}");
+					None
+				}
+			});
 
 		let canvas = context.painter.context.new_canvas(
 			(CANVAS_WIDTH, CANVAS_HEIGHT),
@@ -135,14 +262,24 @@ impl App {
 			Default::default(),
 		);
 
+		let settings = SettingsStore::new(settings, &state, &context.audio);
+
 		Self {
-			scene: Scene::new(&mut context, &state),
+			scene: Scene::new(&mut context, &state, user_games),
 			context,
 			state,
 
 			canvas,
 			canvas_offset: Point::default(),
 			canvas_scale: 1.0,
+
+			gamepads: Gamepads::new(),
+			settings,
+
+			#[cfg(not(target_arch = "wasm32"))]
+			debug_server,
+			#[cfg(not(target_arch = "wasm32"))]
+			trace,
 		}
 	}
 
@@ -171,8 +308,22 @@ impl EventHandler for App {
 		ctx.tooltip.update(&ctx.time);
 
 		ctx.icons_anim.update(&ctx.time);
+		ctx.input.set_buttons_pressed(self.gamepads.pressed());
+
+		#[cfg(not(target_arch = "wasm32"))]
+		if let Some(server) = &mut self.debug_server {
+			server.update(&mut self.state.emu, self.state.tape.as_mut());
+		}
 
 		self.scene.update(ctx, &mut self.state);
+		self.settings.update(&self.state, &ctx.audio);
+
+		#[cfg(not(target_arch = "wasm32"))]
+		if let Some(file) = &mut self.trace
+			&& file.write(&mut self.state.emu).is_err()
+		{
+			self.trace = None;
+		}
 	}
 	fn draw(&mut self) {
 		self.update_canvas_scaling();
@@ -242,4 +393,10 @@ impl EventHandler for App {
 		ctx.input.keys_pressed.remove(&key);
 		ctx.input.keys_just_released.insert(key);
 	}
+	fn quit_requested_event(&mut self) {
+		#[cfg(not(target_arch = "wasm32"))]
+		if let Some(tape) = &self.state.tape {
+			tape.save();
+		}
+	}
 }
@@ -10,3 +10,12 @@ pub use wasm::*;
 mod native;
 #[cfg(not(target_arch = "wasm32"))]
 pub use native::*;
+
+/// Buzz sound parameters
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub struct BuzzParams {
+	/// XO-CHIP 1-bit audio pattern, default buzz tone is played if `None`
+	pub pattern: Option<[u8; 16]>,
+	/// Pattern playback rate in Hz
+	pub rate: f32,
+}
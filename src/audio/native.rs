@@ -1,13 +1,26 @@
-use std::mem;
+use std::{
+	mem,
+	sync::{
+		Arc, Mutex,
+		atomic::{AtomicBool, Ordering},
+	},
+	time::Duration,
+};
 
-use rodio::{OutputStream, OutputStreamBuilder, Sink, Source, buffer::SamplesBuffer};
+use rodio::{
+	OutputStream, OutputStreamBuilder, Sink, Source, buffer::SamplesBuffer, source::TriangleWave,
+};
 
 use crate::app::Time;
 
+use super::BuzzParams;
+
 /// Default sound samplerate
 /// Every audio file should have this samplerate, otherwise it will sound wrong
 pub const SAMPLERATE: u32 = 24_000;
 const SINKS_COUNT: usize = 16;
+/// How often the sounds check whether the audio is muted
+const MUTE_PERIOD: Duration = Duration::from_millis(10);
 
 /// This structure fixes Rust's lack of custom alignment in `include_bytes!()`
 /// Thanks to this blog post https://jack.wrenn.fyi/blog/include-transmute
@@ -89,12 +102,76 @@ impl Sound {
 	}
 }
 
+/// Handle to update parameters of the playing buzz sound
+#[derive(Debug, Default, Clone)]
+pub struct BuzzHandle(Arc<Mutex<BuzzParams>>);
+impl BuzzHandle {
+	pub fn set(&self, params: BuzzParams) {
+		if let Ok(mut cur) = self.0.lock() {
+			*cur = params;
+		}
+	}
+}
+
+/// Buzz sound source
+/// Plays the XO-CHIP audio pattern or the default triangle tone if there is no pattern
+struct BuzzSource {
+	handle: BuzzHandle,
+	params: BuzzParams,
+	tone: TriangleWave,
+	/// Current position in the pattern in bits
+	pos: f32,
+}
+impl BuzzSource {
+	const SAMPLERATE: u32 = 48_000;
+	const PATTERN_BITS: f32 = 128.0;
+}
+impl Iterator for BuzzSource {
+	type Item = f32;
+
+	fn next(&mut self) -> Option<f32> {
+		// Don't block the audio thread, just use the last known params
+		if let Ok(params) = self.handle.0.try_lock() {
+			self.params = *params;
+		}
+
+		let Some(pattern) = self.params.pattern else {
+			return self.tone.next();
+		};
+
+		let bit = self.pos as usize;
+		let on = pattern[bit / 8] & (0x80 >> (bit % 8)) > 0;
+
+		self.pos += self.params.rate / Self::SAMPLERATE as f32;
+		self.pos %= Self::PATTERN_BITS;
+
+		Some(if on { 1.0 } else { -1.0 })
+	}
+}
+impl Source for BuzzSource {
+	fn current_span_len(&self) -> Option<usize> {
+		None
+	}
+	fn channels(&self) -> u16 {
+		1
+	}
+	fn sample_rate(&self) -> u32 {
+		Self::SAMPLERATE
+	}
+	fn total_duration(&self) -> Option<Duration> {
+		None
+	}
+}
+
 /// Audio manager
+/// `Muted` audio has no output device at all, while `Normal` audio can be muted on the fly
 #[allow(clippy::large_enum_variant)]
 pub enum Audio {
 	Normal {
 		stream: OutputStream,
 		sinks: [Sink; SINKS_COUNT],
+		/// Shared with every created sound
+		muted: Arc<AtomicBool>,
 	},
 	Muted,
 }
@@ -116,14 +193,43 @@ impl Audio {
 			sink
 		});
 
-		Self::Normal { stream, sinks }
+		Self::Normal {
+			stream,
+			sinks,
+			muted: Arc::default(),
+		}
+	}
+
+	/// Whether nothing is heard
+	pub fn is_muted(&self) -> bool {
+		match self {
+			Self::Normal { muted, .. } => muted.load(Ordering::Relaxed),
+			Self::Muted => true,
+		}
+	}
+	/// Silence or unmute all sounds, does nothing if there is no output device
+	pub fn set_muted(&mut self, mute: bool) {
+		if let Self::Normal { muted, .. } = self {
+			muted.store(mute, Ordering::Relaxed);
+		}
 	}
 
 	pub fn new_sound<S: Source + Send + 'static>(&mut self, source: S) -> Sound {
-		let Self::Normal { stream, .. } = self else {
+		let Self::Normal { stream, muted, .. } = self else {
 			return Sound::Muted;
 		};
 
+		let muted = muted.clone();
+		let source = source
+			.amplify(1.0)
+			.periodic_access(MUTE_PERIOD, move |source| {
+				source.set_factor(if muted.load(Ordering::Relaxed) {
+					0.0
+				} else {
+					1.0
+				});
+			});
+
 		let sink = Sink::connect_new(stream.mixer());
 		sink.append(source);
 		sink.pause();
@@ -143,10 +249,27 @@ impl Audio {
 		}
 	}
 
+	/// Create the CHIP-8 buzz sound
+	pub fn new_buzz_sound(&mut self) -> (Sound, BuzzHandle) {
+		let handle = BuzzHandle::default();
+		let source = BuzzSource {
+			handle: handle.clone(),
+			params: BuzzParams::default(),
+			tone: TriangleWave::new(200.0),
+			pos: 0.0,
+		};
+
+		let sound = self.new_sound(source.high_pass(500).amplify(0.2));
+		(sound, handle)
+	}
+
 	pub fn play(&mut self, data: SoundData) {
-		let Self::Normal { sinks, .. } = self else {
+		let Self::Normal { sinks, muted, .. } = self else {
 			return;
 		};
+		if muted.load(Ordering::Relaxed) {
+			return;
+		}
 
 		// Find the first empty sink
 		let Some(sink) = sinks.iter().find(|s| s.empty()) else {
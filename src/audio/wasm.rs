@@ -1,5 +1,7 @@
 use crate::app::Time;
 
+use super::BuzzParams;
+
 /// Sound data
 #[derive(Debug, Clone, Copy)]
 pub struct SoundData(pub ());
@@ -31,12 +33,29 @@ impl Sound {
 	}
 }
 
+/// Handle to update parameters of the playing buzz sound
+#[derive(Debug, Default, Clone)]
+pub struct BuzzHandle;
+impl BuzzHandle {
+	pub fn set(&self, _params: BuzzParams) {
+		/* no-op */
+	}
+}
+
 /// Audio manager
 pub struct Audio;
 impl Audio {
 	pub fn new(_muted: bool) -> Self {
 		Self
 	}
+
+	pub fn is_muted(&self) -> bool {
+		true
+	}
+	pub fn set_muted(&mut self, _mute: bool) {
+		/* no-op */
+	}
+
 	pub fn new_sound<S>(&mut self, _source: S) -> Sound {
 		Sound
 	}
@@ -44,6 +63,10 @@ impl Audio {
 		Sound
 	}
 
+	pub fn new_buzz_sound(&mut self) -> (Sound, BuzzHandle) {
+		(Sound, BuzzHandle)
+	}
+
 	pub fn play(&mut self, _data: SoundData) {
 		/* no-op */
 	}
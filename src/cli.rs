@@ -1,4 +1,122 @@
-use std::{path::PathBuf, process::exit};
+use std::{fmt::Display, ops::RangeInclusive, path::PathBuf, process::exit, str::FromStr};
+
+use crate::{
+	emu::{Breakpoint, Emu, MemoryPolicy, Mode, QuirksPreset, TraceFilter, parse_addr_range},
+	headless::ScreenDump,
+	palette::Palette,
+	state::BoardSide,
+};
+
+/// Highest number of instructions per second accepted with `--ips`
+const MAX_IPS: u32 = 100_000;
+/// Range of the window scale accepted with `--scale`
+const SCALE_RANGE: RangeInclusive<f32> = 0.5..=4.0;
+
+/// Command line option
+struct Opt {
+	/// Long name without the dashes
+	name: &'static str,
+	short: Option<char>,
+	/// Value placeholder, e.g. `<file>`, empty for flags
+	value: &'static str,
+	/// Help lines
+	help: &'static [&'static str],
+}
+impl Opt {
+	const fn flag(name: &'static str, help: &'static [&'static str]) -> Self {
+		Self {
+			name,
+			short: None,
+			value: "",
+			help,
+		}
+	}
+	const fn value(name: &'static str, value: &'static str, help: &'static [&'static str]) -> Self {
+		Self {
+			name,
+			short: None,
+			value,
+			help,
+		}
+	}
+
+	/// Option with the name, e.g. `--seed` or `-h`
+	fn find(name: &str) -> Option<&'static Self> {
+		OPTIONS.iter().flat_map(|group| group.iter()).find(|opt| {
+			name.strip_prefix("--") == Some(opt.name)
+				|| opt.short.is_some_and(|short| name == format!("-{short}"))
+		})
+	}
+
+	/// Option as it is shown in the help, e.g. `--seed <number>`
+	fn usage(&self) -> String {
+		let mut usage = match self.short {
+			Some(short) => format!("-{short}, --{}", self.name),
+			None => format!("--{}", self.name),
+		};
+		if !self.value.is_empty() {
+			usage += " ";
+			usage += self.value;
+		}
+		usage
+	}
+}
+
+/// Options in the help order, grouped by what they are for
+#[rustfmt::skip]
+const OPTIONS: &[&[Opt]] = &[
+	&[
+		Opt::value("speed", "<factor>", &["Execution speed from 0 to 5, 1 is the normal speed"]),
+		Opt::value("ips", "<number>", &["Instructions per second at the normal speed"]),
+		Opt::value("quirks", "<preset>", &["Quirks profile: legacy, vip, chip48, schip or modern"]),
+		Opt::value("mode", "<mode>", &["Instruction set: chip8, schip or xochip"]),
+		Opt::value("seed", "<number>", &["Seed of the emulator random generator"]),
+		Opt::flag("strict", &["Halt on emulator errors instead of carrying on"]),
+		Opt::value("memory", "<policy>", &["Out of bounds memory accesses: wrap or fault (default)"]),
+	],
+	&[
+		Opt::flag("muted", &["Mute audio"]),
+		Opt::value("palette", "<name>", &["Screen colors: green (default), amber, gray or blue"]),
+		Opt::value("scale", "<factor>", &["Window size relative to 700x700, from 0.5 to 4"]),
+		Opt::flag("fullscreen", &["Start in fullscreen"]),
+		Opt::flag("start-powered", &["Turn the board on right away"]),
+		Opt::value("side", "<side>", &["Board side to start on: front or back"]),
+		Opt::flag("no-heat", &["Keep the CPU cool, so the board never explodes"]),
+		Opt::value("roms", "<dir>", &["Directory with ROMs to list in the cartridge picker"]),
+	],
+	&[
+		Opt::value("break", "<breakpoint>", &[
+			"Pause at the address and/or on the register condition,",
+			"e.g. \"0x234\", \"0x234 if v3 == 5\" or \"if v0 >= 0x10\"",
+		]),
+		Opt::value("watch", "<range>", &["Pause on Fx33/Fx55 writes to the memory, e.g. \"0x300-0x30F\""]),
+		Opt::value("debug-port", "<port>", &["Accept debugger commands on the localhost TCP port"]),
+	],
+	&[
+		Opt::value("trace", "<file>", &["Write every executed instruction into the binary trace file"]),
+		Opt::value("trace-range", "<range>", &["Trace only instructions in the memory range, e.g. \"0x200-0x2FF\""]),
+		Opt::value("trace-frames", "<n-m>", &["Trace only instructions executed in the frames, e.g. \"60-120\""]),
+		Opt::value("trace-text", "<file>", &["Print the binary trace file as text and exit"]),
+	],
+	&[
+		Opt::value("record", "<file>", &["Record the session from the power-on into the movie file"]),
+		Opt::value("play", "<file>", &["Play the movie file, works in the headless mode too"]),
+	],
+	&[
+		Opt::flag("headless", &["Run the ROM without a window and audio, then exit"]),
+		Opt::value("frames", "<number>", &[
+			"Number of 60 Hz frames to run in the headless mode,",
+			"the whole movie is run if not specified",
+		]),
+		Opt::value("screen", "<format>", &["Print the final screen in the headless mode: ascii, pbm or hash"]),
+		Opt::flag("regs", &["Print the final registers in the headless mode"]),
+		Opt::flag("disasm", &["Print the ROM disassembly and exit"]),
+		Opt::flag("verify", &["Check screens of the bundled games against the golden ones"]),
+		Opt::value("assemble", "<file>", &["Assemble the Octo source into a .ch8 file next to it and exit"]),
+		Opt::flag("hello", &["Say \"hello\" to CHIP MAXIMATOR"]),
+		Opt { short: Some('h'), ..Opt::flag("help", &["Print this message"]) },
+	],
+];
 
 fn print_help() {
 	println!("CHIP MAXIMATOR by bogdanov v{}", env!("CARGO_PKG_VERSION"));
@@ -6,10 +124,28 @@ fn print_help() {
 	println!("USAGE:");
 	println!("    {bin} [options...] [rom]", bin = env!("CARGO_BIN_NAME"));
 	println!();
+	println!("    Values can also be passed as \"--option=value\".");
+	println!("    Options override the settings saved by the app.");
+	println!();
 	println!("OPTIONS:");
-	println!("    --muted      Mute audio");
-	println!("    --hello      Say \"hello\" to CHIP MAXIMATOR");
-	println!("    -h, --help   Print this message");
+
+	for (i, group) in OPTIONS.iter().enumerate() {
+		if i > 0 {
+			println!();
+		}
+
+		for opt in group.iter() {
+			let mut help = opt.help.iter();
+			println!(
+				"    {:<21} {}",
+				opt.usage(),
+				help.next().copied().unwrap_or_default()
+			);
+			for line in help {
+				println!("{:26}{line}", "");
+			}
+		}
+	}
 }
 
 /// Command line interface
@@ -17,41 +153,309 @@ fn print_help() {
 pub struct Cli {
 	pub muted: bool,
 	pub rom_path: Option<PathBuf>,
+	/// Execution speed which overrides the saved one
+	pub speed: Option<f32>,
+	/// Instructions per second at the normal speed
+	pub ips: Option<u32>,
+	/// Quirks profile which overrides the one of the loaded ROM
+	pub quirks: Option<QuirksPreset>,
+	/// Instruction set which overrides the one of the loaded ROM
+	pub mode: Option<Mode>,
+	/// Random seed of the emulator, picked from the current time if not specified
+	pub seed: Option<u64>,
+	/// Directory with user ROMs for the cartridge picker
+	pub roms_dir: Option<PathBuf>,
+	/// Halt on emulator errors
+	pub strict: bool,
+	/// How out of bounds memory accesses are handled
+	pub memory_policy: MemoryPolicy,
+
+	/// Colors of the game display
+	pub palette: Palette,
+	/// Window size relative to the canvas size
+	pub scale: Option<f32>,
+	pub fullscreen: bool,
+	/// Turn the board on at the start
+	pub start_powered: bool,
+	/// Board side which overrides the saved one
+	pub side: Option<BoardSide>,
+	/// Never let the CPU heat up
+	pub no_heat: bool,
+
+	/// Debugger breakpoints
+	pub breakpoints: Vec<Breakpoint>,
+	/// Debugger watched memory ranges
+	pub watchpoints: Vec<RangeInclusive<u16>>,
+	/// Localhost TCP port of the debug server
+	pub debug_port: Option<u16>,
+
+	/// Binary trace file to write
+	pub trace: Option<PathBuf>,
+	/// Which instructions to trace
+	pub trace_filter: TraceFilter,
+	/// Binary trace file to print as text
+	pub trace_text: Option<PathBuf>,
+
+	/// Movie file to record
+	pub record: Option<PathBuf>,
+	/// Movie file to play
+	pub play: Option<PathBuf>,
+
+	/// Run without a window and audio
+	pub headless: bool,
+	/// Number of frames to run in the headless mode
+	pub frames: Option<u32>,
+	/// Format of the screen dump printed after the headless run
+	pub screen: Option<ScreenDump>,
+	/// Print registers after the headless run
+	pub regs: bool,
+	/// Print the ROM disassembly
+	pub disasm: bool,
+	/// Check the bundled games against the golden screen hashes
+	pub verify: bool,
+	/// Octo source to assemble
+	pub assemble: Option<PathBuf>,
 }
 impl Cli {
+	/// Parse the process arguments, exit with the error if any of them is invalid
 	pub fn parse(&mut self) {
-		let mut args = std::env::args();
-		args.next();
+		if let Err(e) = self.parse_args(std::env::args().skip(1)) {
+			eprintln!("ERROR: {e}");
+			exit(1);
+		}
+	}
 
-		for arg in args {
+	fn parse_args(&mut self, args: impl IntoIterator<Item = String>) -> Result<(), String> {
+		let mut args = args.into_iter();
+
+		while let Some(arg) = args.next() {
 			if !arg.starts_with('-') {
 				if self.rom_path.is_some() {
-					eprintln!("ERROR: you can't specify more than one ROM files");
-					exit(1);
+					return Err("you can't specify more than one ROM files".to_string());
 				}
 
 				self.rom_path = Some(PathBuf::from(arg));
 				continue;
 			}
 
-			match arg.as_str() {
-				"-h" | "--help" => {
-					print_help();
-					exit(0);
-				}
-				"--hello" => {
-					println!("hi");
-					exit(0);
-				}
+			let (name, inline_value) = match arg.split_once('=') {
+				Some((name, value)) => (name, Some(value.to_string())),
+				None => (arg.as_str(), None),
+			};
+			let Some(opt) = Opt::find(name) else {
+				return Err(format!("unknown option \"{name}\", see \"--help\""));
+			};
 
-				"--muted" => self.muted = true,
+			let value = match (opt.value.is_empty(), inline_value) {
+				(true, None) => String::new(),
+				(true, Some(_)) => return Err(format!("\"{name}\" doesn't take a value")),
+				(false, Some(value)) => value,
+				(false, None) => args
+					.next()
+					.ok_or_else(|| format!("\"{name}\" expects {}", opt.value))?,
+			};
 
-				opt => {
-					print_help();
-					eprintln!("\nERROR: unknown option \"{opt}\"");
-					exit(1);
-				}
+			self.set(opt.name, value)?;
+		}
+
+		Ok(())
+	}
+
+	/// Set the option with the long name
+	fn set(&mut self, name: &str, value: String) -> Result<(), String> {
+		match name {
+			"help" => {
+				print_help();
+				exit(0);
+			}
+			"hello" => {
+				println!("hi");
+				exit(0);
 			}
+
+			"speed" => {
+				self.speed = Some(parse_in_range(
+					&value,
+					"speed",
+					Emu::MIN_SPEED..=Emu::MAX_SPEED,
+				)?)
+			}
+			"ips" => {
+				self.ips = Some(parse_in_range(
+					&value,
+					"number of instructions per second",
+					1..=MAX_IPS,
+				)?)
+			}
+			"quirks" => {
+				let preset = QuirksPreset::from_name(&value).ok_or_else(|| {
+					unknown(
+						"quirks preset",
+						&value,
+						"legacy, vip, chip48, schip or modern",
+					)
+				})?;
+				self.quirks = Some(preset);
+			}
+			"mode" => {
+				let mode = Mode::from_name(&value)
+					.ok_or_else(|| unknown("mode", &value, "chip8, schip or xochip"))?;
+				self.mode = Some(mode);
+			}
+			"seed" => self.seed = Some(parse(&value, "seed")?),
+			"strict" => self.strict = true,
+			"memory" => {
+				self.memory_policy = MemoryPolicy::from_name(&value)
+					.ok_or_else(|| unknown("memory policy", &value, "wrap or fault"))?;
+			}
+
+			"muted" => self.muted = true,
+			"palette" => {
+				self.palette = Palette::from_name(&value)
+					.ok_or_else(|| unknown("palette", &value, "green, amber, gray or blue"))?;
+			}
+			"scale" => self.scale = Some(parse_in_range(&value, "scale", SCALE_RANGE)?),
+			"fullscreen" => self.fullscreen = true,
+			"start-powered" => self.start_powered = true,
+			"side" => {
+				let side = BoardSide::from_name(&value)
+					.ok_or_else(|| unknown("board side", &value, "front or back"))?;
+				self.side = Some(side);
+			}
+			"no-heat" => self.no_heat = true,
+			"roms" => self.roms_dir = Some(PathBuf::from(value)),
+
+			"break" => {
+				let bp = Breakpoint::parse(&value).ok_or_else(|| invalid("breakpoint", &value))?;
+				self.breakpoints.push(bp);
+			}
+			"watch" => {
+				let range =
+					parse_addr_range(&value).ok_or_else(|| invalid("memory range", &value))?;
+				self.watchpoints.push(range);
+			}
+			"debug-port" => self.debug_port = Some(parse(&value, "port")?),
+
+			"trace" => self.trace = Some(PathBuf::from(value)),
+			"trace-range" => {
+				let range =
+					parse_addr_range(&value).ok_or_else(|| invalid("memory range", &value))?;
+				self.trace_filter.addrs = Some(range);
+			}
+			"trace-frames" => {
+				let range =
+					parse_frame_range(&value).ok_or_else(|| invalid("range of frames", &value))?;
+				self.trace_filter.frames = Some(range);
+			}
+			"trace-text" => self.trace_text = Some(PathBuf::from(value)),
+
+			"record" => self.record = Some(PathBuf::from(value)),
+			"play" => self.play = Some(PathBuf::from(value)),
+
+			"headless" => self.headless = true,
+			"frames" => self.frames = Some(parse(&value, "number of frames")?),
+			"screen" => {
+				let format = ScreenDump::from_name(&value)
+					.ok_or_else(|| unknown("screen format", &value, "ascii, pbm or hash"))?;
+				self.screen = Some(format);
+			}
+			"regs" => self.regs = true,
+			"disasm" => self.disasm = true,
+			"verify" => self.verify = true,
+			"assemble" => self.assemble = Some(PathBuf::from(value)),
+
+			_ => unreachable!("option \"--{name}\" is not handled"),
 		}
+
+		Ok(())
+	}
+}
+
+fn invalid(what: &str, value: &str) -> String {
+	format!("invalid {what} \"{value}\"")
+}
+fn unknown(what: &str, value: &str, expected: &str) -> String {
+	format!("unknown {what} \"{value}\", expected {expected}")
+}
+
+fn parse<T: FromStr>(value: &str, what: &str) -> Result<T, String> {
+	value.parse().map_err(|_| invalid(what, value))
+}
+fn parse_in_range<T>(value: &str, what: &str, range: RangeInclusive<T>) -> Result<T, String>
+where
+	T: FromStr + PartialOrd + Display,
+{
+	value
+		.parse()
+		.ok()
+		.filter(|n| range.contains(n))
+		.ok_or_else(|| {
+			format!(
+				"{}, expected a number from {} to {}",
+				invalid(what, value),
+				range.start(),
+				range.end()
+			)
+		})
+}
+
+/// Parse a range of frames like `60` or `60-120`
+fn parse_frame_range(s: &str) -> Option<RangeInclusive<u32>> {
+	let (start, end) = match s.split_once('-') {
+		Some((start, end)) => (start.trim().parse().ok()?, end.trim().parse().ok()?),
+		None => {
+			let frame = s.trim().parse().ok()?;
+			(frame, frame)
+		}
+	};
+
+	(start <= end).then_some(start..=end)
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	fn parse_args(args: &[&str]) -> Result<Cli, String> {
+		let mut cli = Cli::default();
+		cli.parse_args(args.iter().map(|arg| arg.to_string()))?;
+		Ok(cli)
+	}
+
+	#[test]
+	fn options() {
+		let cli = parse_args(&[
+			"--speed=2.5",
+			"--ips",
+			"600",
+			"--mode=xochip",
+			"--side",
+			"back",
+			"--no-heat",
+			"--palette=amber",
+			"game.ch8",
+		])
+		.unwrap();
+
+		assert_eq!(cli.speed, Some(2.5));
+		assert_eq!(cli.ips, Some(600));
+		assert_eq!(cli.mode, Some(Mode::XoChip));
+		assert_eq!(cli.side, Some(BoardSide::Back));
+		assert!(cli.no_heat);
+		assert_eq!(cli.palette, Palette::Amber);
+		assert_eq!(cli.rom_path, Some(PathBuf::from("game.ch8")));
+
+		let err = |args: &[&str]| parse_args(args).err().unwrap();
+		assert_eq!(
+			err(&["--speed", "9"]),
+			"invalid speed \"9\", expected a number from 0 to 5"
+		);
+		assert_eq!(
+			err(&["--side=top"]),
+			"unknown board side \"top\", expected front or back"
+		);
+		assert_eq!(err(&["--scale"]), "\"--scale\" expects <factor>");
+		assert_eq!(err(&["--muted=yes"]), "\"--muted\" doesn't take a value");
+		assert_eq!(err(&["--foo"]), "unknown option \"--foo\", see \"--help\"");
 	}
 }
@@ -0,0 +1,74 @@
+use std::fmt;
+
+#[cfg(not(target_arch = "wasm32"))]
+use std::path::PathBuf;
+
+/// Syntax error in a config file
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ConfigError {
+	/// Line number starting from 1
+	pub line: usize,
+	pub msg: String,
+}
+impl fmt::Display for ConfigError {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		write!(f, "line {}: {}", self.line, self.msg)
+	}
+}
+
+/// Single `key = value` line of a config file
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ConfigEntry<'a> {
+	pub line: usize,
+	pub key: &'a str,
+	pub value: &'a str,
+}
+impl ConfigEntry<'_> {
+	pub fn error(&self, msg: impl Into<String>) -> ConfigError {
+		ConfigError {
+			line: self.line,
+			msg: msg.into(),
+		}
+	}
+}
+
+/// Parse `key = value` lines of a config file, empty lines and `#` comments are skipped
+pub fn parse_config(source: &str) -> Result<Vec<ConfigEntry<'_>>, ConfigError> {
+	let mut entries = Vec::new();
+
+	for (i, line) in source.lines().enumerate() {
+		let line_num = i + 1;
+		let line = line.split('#').next().unwrap_or_default().trim();
+		if line.is_empty() {
+			continue;
+		}
+
+		let Some((key, value)) = line.split_once('=') else {
+			return Err(ConfigError {
+				line: line_num,
+				msg: format!("expected \"key = value\", got \"{line}\""),
+			});
+		};
+
+		entries.push(ConfigEntry {
+			line: line_num,
+			key: key.trim(),
+			value: value.trim(),
+		});
+	}
+
+	Ok(entries)
+}
+
+/// Directory of the user config files
+/// `$XDG_CONFIG_HOME/chip-maximator`, `%APPDATA%\chip-maximator` or `~/.config/chip-maximator`
+#[cfg(not(target_arch = "wasm32"))]
+pub fn config_dir() -> Option<PathBuf> {
+	let var = |name| std::env::var_os(name).filter(|dir| !dir.is_empty());
+
+	let base = match var("XDG_CONFIG_HOME").or_else(|| var("APPDATA")) {
+		Some(dir) => PathBuf::from(dir),
+		None => PathBuf::from(var("HOME")?).join(".config"),
+	};
+	Some(base.join("chip-maximator"))
+}
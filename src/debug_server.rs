@@ -0,0 +1,438 @@
+use std::{
+	fmt::Write as _,
+	io::{self, BufRead, BufReader, Write},
+	net::{Ipv4Addr, TcpListener, TcpStream},
+};
+
+use crate::{
+	emu::{Breakpoint, Emu, Hit, parse_addr_range, parse_number},
+	headless::{ScreenDump, dump_screen},
+	movie::{MovieTape, step_frame},
+};
+
+const HELP: &str = "\
+regs                     print registers
+reg <name> <value>       set a register: v0-vf, pc, i, sp, dt or st
+mem <addr> [len]         print memory bytes
+write <addr> <bytes...>  write memory bytes
+break [breakpoint]       add a breakpoint or list all of them
+delete <breakpoint>      remove a breakpoint, or all of them with \"delete all\"
+watch [range]            add a watchpoint or list all of them
+unwatch <range>          remove a watchpoint, or all of them with \"unwatch all\"
+pause                    pause the execution
+continue                 resume the execution
+step [count]             execute instructions while paused
+frame [count]            run up to 600 60 Hz frames while paused
+status                   print whether the emulator is running
+errors                   print recent emulator errors
+screen                   print the screen
+help                     print this message";
+
+/// Longest accepted request, clients sending longer ones are dropped
+const MAX_LINE_LEN: usize = 1024;
+/// Most frames run by a single request, the board is frozen while they run
+const MAX_FRAMES: u16 = 600;
+
+/// Line-based debug protocol over a localhost TCP socket
+///
+/// Each request is a single line, each response is zero or more lines of data followed
+/// by `OK` or `ERR <message>`. `STOP <reason>` is sent to everyone when a breakpoint
+/// or a watchpoint is hit.
+pub struct DebugServer {
+	listener: TcpListener,
+	clients: Vec<Client>,
+	/// Last hit sent to the clients
+	last_hit: Option<Hit>,
+}
+
+struct Client {
+	stream: BufReader<TcpStream>,
+	/// Incomplete line received so far
+	line: Vec<u8>,
+	/// Responses not accepted by the socket yet
+	out: Vec<u8>,
+	/// Whether no more requests are read, the client is dropped once all responses are sent
+	closing: bool,
+}
+
+impl DebugServer {
+	pub fn bind(port: u16) -> io::Result<Self> {
+		let listener = TcpListener::bind((Ipv4Addr::LOCALHOST, port))?;
+		listener.set_nonblocking(true)?;
+
+		Ok(Self {
+			listener,
+			clients: Vec::new(),
+			last_hit: None,
+		})
+	}
+
+	/// Accept new clients and execute all received requests, never blocks
+	/// Frames are stepped through the movie tape so a recording stays in sync
+	pub fn update(&mut self, emu: &mut Emu, mut tape: Option<&mut MovieTape>) {
+		while let Ok((stream, _)) = self.listener.accept() {
+			if let Some(client) = Client::new(stream) {
+				self.clients.push(client);
+			}
+		}
+
+		self.clients
+			.retain_mut(|client| client.update(emu, tape.as_deref_mut()).is_ok());
+
+		// Notify everyone about a new hit
+		let hit = emu.debugger.hit;
+		if let Some(hit) = hit
+			&& self.last_hit != Some(hit)
+		{
+			let msg = format!("STOP {hit}\n");
+			self.clients
+				.retain_mut(|client| client.write(msg.as_bytes()).is_ok());
+		}
+		self.last_hit = hit;
+	}
+}
+
+impl Client {
+	fn new(stream: TcpStream) -> Option<Self> {
+		stream.set_nonblocking(true).ok()?;
+
+		Some(Self {
+			stream: BufReader::new(stream),
+			line: Vec::new(),
+			out: Vec::new(),
+			closing: false,
+		})
+	}
+
+	/// Returns an error if the client has disconnected or has to be dropped
+	fn update(&mut self, emu: &mut Emu, mut tape: Option<&mut MovieTape>) -> io::Result<()> {
+		// Don't read new requests until the client takes the previous responses
+		while !self.closing && self.out.is_empty() {
+			// Received bytes are appended to the line even if the read would block
+			let res = self.stream.read_until(b'\n', &mut self.line);
+			if self.line.len() > MAX_LINE_LEN {
+				self.closing = true;
+				self.write(b"ERR request is too long\n")?;
+				break;
+			}
+
+			match res {
+				Ok(0) => self.closing = true,
+				Ok(_) if self.line.ends_with(b"\n") => {
+					let request = String::from_utf8_lossy(&self.line);
+					let response = match execute(emu, tape.as_deref_mut(), request.trim()) {
+						Ok(data) => format!("{data}OK\n"),
+						Err(e) => format!("ERR {e}\n"),
+					};
+					self.line.clear();
+					self.write(response.as_bytes())?;
+				}
+				Ok(_) => (),
+				Err(e) if e.kind() == io::ErrorKind::WouldBlock => break,
+				Err(e) => return Err(e),
+			}
+		}
+
+		self.flush()?;
+		if self.closing && self.out.is_empty() {
+			return Err(io::ErrorKind::ConnectionAborted.into());
+		}
+		Ok(())
+	}
+
+	/// Queue the bytes and send as many of the queued bytes as the socket accepts
+	fn write(&mut self, bytes: &[u8]) -> io::Result<()> {
+		self.out.extend_from_slice(bytes);
+		self.flush()
+	}
+	fn flush(&mut self) -> io::Result<()> {
+		while !self.out.is_empty() {
+			match self.stream.get_mut().write(&self.out) {
+				Ok(0) => return Err(io::ErrorKind::WriteZero.into()),
+				Ok(n) => {
+					self.out.drain(..n);
+				}
+				Err(e) if e.kind() == io::ErrorKind::WouldBlock => break,
+				Err(e) => return Err(e),
+			}
+		}
+		Ok(())
+	}
+}
+
+/// Execute a single request
+/// Returns the response data, each line ends with a newline
+pub fn execute(
+	emu: &mut Emu,
+	mut tape: Option<&mut MovieTape>,
+	request: &str,
+) -> Result<String, String> {
+	let mut args = request.split_whitespace();
+	let Some(cmd) = args.next() else {
+		return Ok(String::new());
+	};
+	let rest = request[cmd.len()..].trim();
+
+	let mut out = String::new();
+
+	match cmd {
+		"help" => out = format!("{HELP}\n"),
+		"regs" => {
+			let _ = write!(
+				out,
+				"PC={:04X} I={:04X} SP={:X} DT={:02X} ST={:02X}",
+				emu.pc, emu.index, emu.sp, emu.delay_timer, emu.sound_timer
+			);
+			for (i, value) in emu.regs.iter().enumerate() {
+				let _ = write!(out, " V{i:X}={value:02X}");
+			}
+			out.push('\n');
+		}
+		"reg" => {
+			let name = args.next().ok_or("expected a register name")?;
+			let value = number(args.next())?;
+
+			match name.to_ascii_lowercase().as_str() {
+				"pc" => emu.pc = value,
+				"i" => emu.index = value,
+				"sp" => emu.sp = value.min(Emu::STACK_SIZE as u16),
+				"dt" => emu.delay_timer = byte(value)?,
+				"st" => emu.sound_timer = byte(value)?,
+				reg => {
+					let x = reg
+						.strip_prefix('v')
+						.and_then(|x| u8::from_str_radix(x, 16).ok())
+						.filter(|x| (*x as usize) < emu.regs.len())
+						.ok_or_else(|| format!("unknown register \"{name}\""))?;
+					emu.regs[x] = byte(value)?;
+				}
+			}
+		}
+		"mem" => {
+			let addr = number(args.next())? as usize;
+			let len = match args.next() {
+				Some(len) => number(Some(len))? as usize,
+				None => 16,
+			};
+			let end = (addr + len).min(emu.memory_size());
+
+			let bytes = emu
+				.memory
+				.get(addr..end)
+				.ok_or("address is out of memory")?;
+			for (i, chunk) in bytes.chunks(16).enumerate() {
+				let _ = write!(out, "{:04X}:", addr + i * 16);
+				for b in chunk {
+					let _ = write!(out, " {b:02X}");
+				}
+				out.push('\n');
+			}
+		}
+		"write" => {
+			let addr = number(args.next())? as usize;
+			let bytes = args
+				.map(|b| byte(number(Some(b))?))
+				.collect::<Result<Vec<_>, _>>()?;
+
+			if addr + bytes.len() > emu.memory_size() {
+				return Err("address is out of memory".to_string());
+			}
+			emu.memory[addr..addr + bytes.len()].copy_from_slice(&bytes);
+		}
+
+		"break" if rest.is_empty() => {
+			for bp in &emu.debugger.breakpoints {
+				let _ = writeln!(out, "{bp}");
+			}
+		}
+		"break" => {
+			let bp = Breakpoint::parse(rest).ok_or("invalid breakpoint")?;
+			emu.debugger.breakpoints.push(bp);
+		}
+		"delete" if rest == "all" => emu.debugger.breakpoints.clear(),
+		"delete" => {
+			let bp = Breakpoint::parse(rest).ok_or("invalid breakpoint")?;
+			let bps = &mut emu.debugger.breakpoints;
+			let i = bps
+				.iter()
+				.position(|b| *b == bp)
+				.ok_or("no such breakpoint")?;
+			bps.remove(i);
+		}
+		"watch" if rest.is_empty() => {
+			for range in &emu.debugger.watchpoints {
+				let _ = writeln!(out, "0x{:04X}-0x{:04X}", range.start(), range.end());
+			}
+		}
+		"watch" => {
+			let range = parse_addr_range(rest).ok_or("invalid memory range")?;
+			emu.debugger.watchpoints.push(range);
+		}
+		"unwatch" if rest == "all" => emu.debugger.watchpoints.clear(),
+		"unwatch" => {
+			let range = parse_addr_range(rest).ok_or("invalid memory range")?;
+			let ranges = &mut emu.debugger.watchpoints;
+			let i = ranges
+				.iter()
+				.position(|r| *r == range)
+				.ok_or("no such watchpoint")?;
+			ranges.remove(i);
+		}
+
+		"pause" => emu.debugger.pause(),
+		"continue" => emu.debugger.resume(),
+		"step" | "frame" => {
+			if !emu.debugger.paused {
+				return Err("the emulator is running, pause it first".to_string());
+			}
+
+			let count = match args.next() {
+				Some(count) => number(Some(count))?,
+				None => 1,
+			};
+			if cmd == "frame" && count > MAX_FRAMES {
+				return Err(format!("at most {MAX_FRAMES} frames can be run at once"));
+			}
+			for _ in 0..count {
+				if cmd == "step" {
+					emu.step_ins();
+				} else {
+					step_frame(emu, tape.as_deref_mut());
+				}
+
+				if emu.debugger.hit.is_some() {
+					break;
+				}
+			}
+			let _ = writeln!(out, "PC={:04X}", emu.pc);
+		}
+		"status" => {
+			let status = match emu.debugger.hit {
+				Some(hit) => format!("paused: {hit}"),
+				None if emu.debugger.paused => "paused".to_string(),
+				None if emu.exited => "exited".to_string(),
+				None => "running".to_string(),
+			};
+			let _ = writeln!(out, "{status}");
+		}
+		"errors" => {
+			for e in emu.errors.iter() {
+				let _ = writeln!(out, "{e}");
+			}
+		}
+		"screen" => out = dump_screen(emu, ScreenDump::Ascii),
+
+		_ => return Err(format!("unknown command \"{cmd}\", try \"help\"")),
+	}
+
+	Ok(out)
+}
+
+/// Number argument of the request, see [`parse_number`]
+fn number(s: Option<&str>) -> Result<u16, String> {
+	let s = s.ok_or("expected a number")?;
+	parse_number(s).ok_or_else(|| format!("invalid number \"{s}\""))
+}
+fn byte(value: u16) -> Result<u8, String> {
+	u8::try_from(value).map_err(|_| format!("{value} doesn't fit into a byte"))
+}
+
+#[cfg(test)]
+mod tests {
+	use std::{
+		io::Read,
+		time::{Duration, Instant},
+	};
+
+	use super::*;
+
+	/// Update the server until the response ends with `end`
+	/// Panics if the response doesn't come in time
+	fn read_response(
+		server: &mut DebugServer,
+		emu: &mut Emu,
+		stream: &mut TcpStream,
+		end: &str,
+	) -> String {
+		let deadline = Instant::now() + Duration::from_secs(5);
+		stream.set_nonblocking(true).unwrap();
+
+		let mut response = String::new();
+		while !response.ends_with(end) {
+			assert!(
+				Instant::now() < deadline,
+				"no response in time, got {response:?}"
+			);
+			server.update(emu, None);
+
+			let mut buf = [0; 256];
+			if let Ok(n) = stream.read(&mut buf) {
+				response.push_str(std::str::from_utf8(&buf[..n]).unwrap());
+			}
+		}
+		response
+	}
+
+	#[test]
+	fn requests() {
+		let mut emu = Emu::default();
+		// v0 := 5; v0 += 1; jump 0x202
+		emu.load(&[0x60, 0x05, 0x70, 0x01, 0x12, 0x02]);
+
+		assert_eq!(
+			execute(&mut emu, None, "mem 0x200 4"),
+			Ok("0200: 60 05 70 01\n".into())
+		);
+		assert!(execute(&mut emu, None, "step").is_err());
+
+		execute(&mut emu, None, "break 0x204 if v0 == 7").unwrap();
+		emu.tick();
+		assert_eq!(emu.debugger.hit, Some(Hit::Breakpoint { pc: 0x204 }));
+		assert_eq!(emu.regs[0], 7);
+
+		execute(&mut emu, None, "delete all").unwrap();
+		execute(&mut emu, None, "reg v0 0x10").unwrap();
+		execute(&mut emu, None, "write 0x203 0x02").unwrap();
+		assert_eq!(execute(&mut emu, None, "step 3"), Ok("PC=0202\n".into()));
+		assert_eq!(emu.regs[0], 0x12);
+
+		emu.debugger.pause();
+		assert!(execute(&mut emu, None, "frame 601").is_err());
+	}
+
+	#[test]
+	fn socket() {
+		let mut server = DebugServer::bind(0).unwrap();
+		let addr = server.listener.local_addr().unwrap();
+		let mut emu = Emu::default();
+
+		let mut stream = TcpStream::connect(addr).unwrap();
+		stream.write_all(b"reg v3 0x2A\nregs\nfoo\n").unwrap();
+		stream.shutdown(std::net::Shutdown::Write).unwrap();
+
+		let response = read_response(&mut server, &mut emu, &mut stream, "\"help\"\n");
+
+		let mut lines = response.lines();
+		assert_eq!(lines.next(), Some("OK"));
+		assert!(lines.next().unwrap().contains("V3=2A"));
+		assert_eq!(lines.next(), Some("OK"));
+		assert_eq!(
+			lines.next(),
+			Some("ERR unknown command \"foo\", try \"help\"")
+		);
+
+		// Requests without the end are not buffered forever
+		let mut stream = TcpStream::connect(addr).unwrap();
+		stream.write_all(&[b'x'; MAX_LINE_LEN + 1]).unwrap();
+		let response = read_response(&mut server, &mut emu, &mut stream, "\n");
+		assert_eq!(response, "ERR request is too long\n");
+		assert!(server.clients.is_empty());
+
+		// Responses larger than the socket buffer are sent in parts
+		emu.mode = crate::emu::Mode::XoChip;
+		let mut stream = TcpStream::connect(addr).unwrap();
+		stream.write_all(b"mem 0 0xFFFF\n").unwrap();
+		let response = read_response(&mut server, &mut emu, &mut stream, "OK\n");
+		assert_eq!(response.lines().count(), Emu::MEMORY_SIZE / 16 + 1);
+	}
+}
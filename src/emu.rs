@@ -1,11 +1,41 @@
-use std::ops::{Deref, DerefMut, Index, IndexMut, Range};
+use std::{
+	ops::{Deref, DerefMut, Index, IndexMut, Range},
+	time::Duration,
+};
 
-use font::FONT;
+use font::{BIG_FONT, FONT};
 
+use crate::util::fnv1a;
+
+pub mod asm;
+mod debugger;
+pub mod disasm;
+mod error;
 mod font;
+#[cfg(test)]
+mod fuzz;
+mod mode;
+mod opcode;
+mod quirks;
+mod rng;
+mod scheduler;
+mod snapshot;
+#[cfg(test)]
+mod tests;
+mod trace;
+
+pub use debugger::*;
+pub use error::*;
+pub use mode::*;
+pub use opcode::*;
+pub use quirks::*;
+pub use rng::*;
+pub use scheduler::*;
+pub use snapshot::*;
+pub use trace::*;
 
 /// Registers, often refered as `V0-VF`
-#[derive(Debug, Default)]
+#[derive(Debug, Default, Clone)]
 pub struct Registers {
 	regs: [u8; Self::COUNT],
 }
@@ -46,9 +76,15 @@ impl DerefMut for Registers {
 }
 
 /// CHIP-8 emulator itself
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct Emu {
-	pub screen: [bool; Self::SCREEN_BUF_SIZE],
+	/// Screen pixels, row stride is the width of the current resolution
+	/// Each pixel is a bitmask of the XO-CHIP planes it is lit on
+	pub screen: [u8; Self::SCREEN_BUF_SIZE],
+	/// Whether the SUPER-CHIP 128x64 hi-res mode is enabled
+	pub hires: bool,
+	/// XO-CHIP bitmask of planes affected by drawing, clearing and scrolling
+	pub planes: u8,
 
 	/// Program counter (PC), points to the current instruction in the memory
 	pub pc: u16,
@@ -67,6 +103,12 @@ pub struct Emu {
 	pub delay_timer: u8,
 	/// Sound timer (ST)
 	pub sound_timer: u8,
+	/// SUPER-CHIP RPL user flags, survive resets
+	pub rpl: [u8; Self::RPL_SIZE],
+	/// XO-CHIP 1-bit audio pattern, default buzz tone is used if not set
+	pub audio_pattern: Option<[u8; Self::AUDIO_PATTERN_SIZE]>,
+	/// XO-CHIP audio pattern pitch
+	pub pitch: u8,
 
 	/// List of pressed keys
 	pub pressed_keys: [bool; Self::KEYS_COUNT],
@@ -77,17 +119,47 @@ pub struct Emu {
 	pub wait_for_keypress: Option<u8>,
 	/// Whether a pressed key check occured
 	pub key_checked: bool,
-	/// Steps per frame multiplier
+	/// Instructions per second multiplier
 	pub speed: f32,
-	/// Whether any kind of error has occurred
-	pub error: bool,
+	/// Number of instructions executed per second at normal speed
+	pub ips: u32,
+	/// Converts elapsed time into 60 Hz ticks
+	pub scheduler: Scheduler,
+	/// Fraction of an instruction left over from the previous tick
+	pub ins_budget: f32,
+	/// Number of ticks elapsed since the last reset
+	pub frame: u32,
+	/// Seed [`Emu::rng`] is restarted with on every reset
+	pub seed: u64,
+	/// Source of `Cxkk` random values
+	pub rng: Rng,
+	/// Behaviours of ambiguous instructions
+	pub quirks: Quirks,
+	/// Supported instruction set
+	pub mode: Mode,
+	/// Whether the program has exited with `00FD`
+	pub exited: bool,
+	/// Recent errors
+	pub errors: ErrorLog,
+	/// Halt on errors instead of carrying on
+	pub strict: bool,
+	/// How out of bounds memory accesses are handled
+	pub memory_policy: MemoryPolicy,
 	/// CPU heat level in range `0.0..=1.0`
 	pub heat: f32,
+	/// Breakpoints, watchpoints and the pause state
+	pub debugger: Debugger,
+	/// Record executed instructions into [`Emu::trace`] if set
+	pub trace_filter: Option<TraceFilter>,
+	/// Executed instructions not yet taken by the trace writer
+	pub trace: Vec<TraceRecord>,
 }
 impl Default for Emu {
 	fn default() -> Self {
 		Self {
-			screen: [false; Self::SCREEN_BUF_SIZE],
+			screen: [0; Self::SCREEN_BUF_SIZE],
+			hires: false,
+			planes: 1,
 
 			pc: 0,
 			sp: 0,
@@ -99,39 +171,74 @@ impl Default for Emu {
 			regs: Registers::default(),
 			delay_timer: 0,
 			sound_timer: 0,
+			rpl: [0; Self::RPL_SIZE],
+			audio_pattern: None,
+			pitch: Self::DEFAULT_PITCH,
 
 			pressed_keys: [false; Self::KEYS_COUNT],
 			cur_ins: (0, 0),
 			wait_for_keypress: None,
 			key_checked: false,
-			speed: 1.0,
-			error: false,
+			speed: Self::DEFAULT_SPEED,
+			ips: Self::DEFAULT_IPS,
+			scheduler: Scheduler::default(),
+			ins_budget: 0.0,
+			frame: 0,
+			seed: 0,
+			rng: Rng::default(),
+			quirks: Quirks::default(),
+			mode: Mode::default(),
+			exited: false,
+			errors: ErrorLog::default(),
+			strict: false,
+			memory_policy: MemoryPolicy::default(),
 			heat: 0.0,
+			debugger: Debugger::default(),
+			trace_filter: None,
+			trace: Vec::new(),
 		}
 	}
 }
 impl Emu {
-	pub const SCREEN_WIDTH: usize = 64;
-	pub const SCREEN_HEIGHT: usize = 32;
+	/// Screen size in the hi-res mode
+	pub const SCREEN_WIDTH: usize = 128;
+	pub const SCREEN_HEIGHT: usize = 64;
+	/// Screen size in the default low-res mode
+	pub const LORES_WIDTH: usize = 64;
+	pub const LORES_HEIGHT: usize = 32;
 	/// Size of the screen data in bytes
 	pub const SCREEN_BUF_SIZE: usize = Self::SCREEN_WIDTH * Self::SCREEN_HEIGHT;
 
 	/// Starting address of programs in the memory
 	/// NOTE: not all of the CHIP-8 programs start at this address for some reason
 	pub const PROGRAM_START_ADDR: usize = 0x200;
-	pub const MEMORY_SIZE: usize = 12288;
+	/// Memory size in XO-CHIP mode
+	pub const MEMORY_SIZE: usize = 0x10000;
+	/// Memory size in CHIP-8 and SUPER-CHIP modes
+	pub const CLASSIC_MEMORY_SIZE: usize = 12288;
 	/// Max program size
 	pub const PROGRAM_SIZE: usize = Self::MEMORY_SIZE - Self::PROGRAM_START_ADDR;
 	pub const STACK_SIZE: usize = 16;
 	pub const KEYS_COUNT: usize = 16;
+	pub const RPL_SIZE: usize = 16;
+	pub const AUDIO_PATTERN_SIZE: usize = 16;
+	/// Pitch at which the audio pattern is played at 4000 Hz
+	pub const DEFAULT_PITCH: u8 = 64;
 
-	/// Default execution speed
+	/// Default number of instructions per tick
 	pub const STEPS_PER_FRAME: u8 = 20;
+	/// Default execution speed
+	pub const DEFAULT_IPS: u32 = Self::STEPS_PER_FRAME as u32 * Scheduler::TICK_RATE;
+	pub const DEFAULT_SPEED: f32 = 1.0;
 	pub const MIN_SPEED: f32 = 0.0;
 	pub const MAX_SPEED: f32 = 5.0;
 
 	/// Each char in the font is 5 bytes long
 	pub const CHAR_HEIGHT: u16 = 5;
+	/// Each char in the big font is 10 bytes long
+	pub const BIG_CHAR_HEIGHT: u16 = 10;
+	/// Address of the big font, it is stored right after the default one
+	pub const BIG_FONT_ADDR: u16 = FONT.len() as u16;
 
 	/// CPU hot level
 	const HOT_LEVEL: f32 = 0.05;
@@ -147,6 +254,7 @@ impl Emu {
 		// Store program
 		let len = program.len().min(Self::PROGRAM_SIZE);
 		self.program[..len].copy_from_slice(&program[..len]);
+		self.program[len..].fill(0);
 
 		self.setup();
 	}
@@ -154,8 +262,10 @@ impl Emu {
 	pub fn setup(&mut self) {
 		self.reset();
 
-		// Load the font first
+		// Load the fonts first
+		let big_font = Self::BIG_FONT_ADDR as usize;
 		self.memory[..FONT.len()].copy_from_slice(FONT);
+		self.memory[big_font..big_font + BIG_FONT.len()].copy_from_slice(BIG_FONT);
 
 		// Load program
 		let start = Self::PROGRAM_START_ADDR;
@@ -170,41 +280,127 @@ impl Emu {
 		*self = Self {
 			program: self.program,
 			speed: self.speed,
+			ips: self.ips,
+			quirks: self.quirks,
+			mode: self.mode,
+			rpl: self.rpl,
 			heat: self.heat,
+			seed: self.seed,
+			strict: self.strict,
+			memory_policy: self.memory_policy,
+			rng: Rng::new(self.seed),
+			debugger: self.debugger.restarted(),
+			trace_filter: self.trace_filter.clone(),
+			trace: std::mem::take(&mut self.trace),
 			..Default::default()
 		};
 	}
+	/// Set the random seed and restart the random generator with it
+	pub fn set_seed(&mut self, seed: u64) {
+		self.seed = seed;
+		self.rng = Rng::new(seed);
+	}
 
-	pub fn update(&mut self) {
+	/// Run as many 60 Hz ticks as fit into the elapsed time
+	pub fn update(&mut self, delta: Duration) {
+		self.update_with(delta, Self::tick);
+	}
+	/// Same as [`Emu::update`], but each tick is run by `tick`, e.g. to record its input
+	pub fn update_with(&mut self, delta: Duration, mut tick: impl FnMut(&mut Self)) {
+		for _ in 0..self.scheduler.advance(delta) {
+			tick(self);
+		}
+	}
+	/// Decrement timers and execute the instructions budget of a single 60 Hz tick
+	pub fn tick(&mut self) {
+		self.frame = self.frame.wrapping_add(1);
 		self.update_timers();
 
-		// Execute current program with speed of 20 instructions per frame
-		let n = (Self::STEPS_PER_FRAME as f32 * self.speed).round() as u8;
-		for _ in 0..n {
+		self.ins_budget += self.ips as f32 * self.speed / Scheduler::TICK_RATE as f32;
+		let n = self.ins_budget.floor();
+		self.ins_budget -= n;
+
+		for _ in 0..n as u32 {
 			self.step();
 		}
 	}
+	/// Execute a single instruction while the debugger is paused
+	pub fn step_ins(&mut self) {
+		self.debugger.begin_step();
+		self.step();
+		self.debugger.end_step();
+	}
+	/// Run a single 60 Hz tick while the debugger is paused
+	/// Stops earlier if a breakpoint or a watchpoint is hit
+	pub fn step_frame(&mut self) {
+		self.step_frame_with(Self::tick);
+	}
+	/// Same as [`Emu::step_frame`], but the tick is run by `tick`, e.g. to record its input
+	pub fn step_frame_with(&mut self, tick: impl FnOnce(&mut Self)) {
+		self.debugger.begin_step();
+		tick(self);
+		self.debugger.end_step();
+	}
+
 	/// Execute the current instruction and increment the program counter by 2
+	/// Does nothing while the debugger is paused
 	pub fn step(&mut self) {
+		if self.debugger.paused {
+			return;
+		}
+
 		// Wrap program counter to the program start if it reached the end of the memeory
 		// Program never stops its execution
-		if self.pc >= self.memory.len() as u16 - 1 {
+		if self.pc as usize >= self.memory_size() - 1 {
+			let error = EmuError::MemoryOutOfBounds {
+				pc: self.pc,
+				addr: self.pc,
+			};
+			if self.memory_policy == MemoryPolicy::Fault && self.report(error) {
+				return;
+			}
 			self.pc = Self::PROGRAM_START_ADDR as u16;
 		}
 
-		// Wait for a keypress
-		if self.wait_for_keypress.is_some() {
+		// Wait for a keypress or stay halted after exit
+		if self.wait_for_keypress.is_some() || self.exited {
+			return;
+		}
+
+		if self.debugger.check_breakpoints(self.pc, &self.regs) {
 			return;
 		}
 
 		let pc = self.pc as usize;
-		self.cur_ins = (self.memory[pc], self.memory[pc + 1]);
+		self.cur_ins = (self.mem_get(pc), self.mem_get(pc + 1));
+
+		let traced = self.record_trace();
+		let errors = self.errors.count;
 
-		self.pc += 2;
+		self.pc = self.pc.wrapping_add(2);
 		self.execute();
+
+		// Blame the instruction for the error
+		if traced
+			&& self.errors.count != errors
+			&& let Some(record) = self.trace.last_mut()
+		{
+			record.error = true;
+		}
+	}
+	/// Record the current instruction if it passes the trace filter
+	fn record_trace(&mut self) -> bool {
+		let Some(filter) = &self.trace_filter else {
+			return false;
+		};
+		if !filter.matches(self.frame, self.pc) {
+			return false;
+		}
+
+		self.trace.push(TraceRecord::new(self));
+		true
 	}
 	/// Execute instruction
-	/// Returns whether the program counter should increment or not
 	fn execute(&mut self) {
 		// (0xAA << 8) | 0xBB = 0xAABB
 		let ins = {
@@ -213,112 +409,149 @@ impl Emu {
 			(a << 8) | b
 		};
 
-		// Split instruction into 4 nibbles
-		let a = ((ins & 0xF000) >> 12) as u8;
-		let b = ((ins & 0x0F00) >> 8) as u8;
-		let c = ((ins & 0x00F0) >> 4) as u8;
-		let d = (ins & 0x000F) as u8;
-
-		let x = b;
-		let y = c;
-		let n = d; // nibble
-		let addr = ins & 0x0FFF; // nnn
-		let byte = (ins & 0x00FF) as u8; // kk
-
 		macro_rules! skip_if {
 			($condition:expr) => {
 				if $condition {
-					self.pc += 2;
+					self.skip();
 				}
 			};
 		}
 
-		match (a, b, c, d) {
+		match Op::decode(ins, self.mode) {
 			// Clear screen
-			(0, 0, 0xe, 0) => self.screen.fill(false),
+			Op::Clear => self.screen_clear(),
 			// Draw sprite
-			(0xd, _, _, _) => self.screen_draw(x, y, n),
+			Op::Draw(x, y, n) => self.screen_draw(x, y, n),
+			// Scroll down by `n` pixels
+			Op::ScrollDown(n) => self.scroll(0, n as isize),
+			// Scroll up by `n` pixels
+			Op::ScrollUp(n) => self.scroll(0, -(n as isize)),
+			// Scroll right by 4 pixels
+			Op::ScrollRight => self.scroll(4, 0),
+			// Scroll left by 4 pixels
+			Op::ScrollLeft => self.scroll(-4, 0),
+			// Select drawing planes
+			Op::Planes(n) => self.planes = n & 0b11,
+			// Disable hi-res mode
+			Op::Lores => self.set_hires(false),
+			// Enable hi-res mode
+			Op::Hires => self.set_hires(true),
+			// Exit
+			Op::Exit => self.exited = true,
 
 			// Return
-			(0, 0, 0xe, 0xe) => {
-				let addr = self.stack_pop();
-				self.jump(addr);
+			Op::Return => {
+				if let Some(addr) = self.stack_pop() {
+					self.jump(addr);
+				}
 			}
 			// Jump
-			(1, _, _, _) => self.jump(addr),
-			// Jump to `addr + V0`
-			(0xb, _, _, _) => self.jump(addr + self.regs[0] as u16),
+			Op::Jump(addr) => self.jump(addr),
+			// Jump to `addr + V0` (or `addr + Vx` with the jump quirk)
+			Op::JumpOffset(x, addr) => {
+				let offset = if self.quirks.jump_vx {
+					self.regs[x]
+				} else {
+					self.regs[0]
+				};
+				self.jump(addr + offset as u16);
+			}
 			// Call a subroutine
-			(2, _, _, _) => {
-				self.stack_push();
-				self.jump(addr);
+			Op::Call(addr) => {
+				if self.stack_push() {
+					self.jump(addr);
+				}
 			}
 
 			// Skip if `Vx == byte`
-			(3, _, _, _) => skip_if!(self.regs[x] == byte),
+			Op::SkipEqByte(x, byte) => skip_if!(self.regs[x] == byte),
 			// Skip if `Vx != byte`
-			(4, _, _, _) => skip_if!(self.regs[x] != byte),
+			Op::SkipNeByte(x, byte) => skip_if!(self.regs[x] != byte),
 			// Skip if `Vx == Vy`
-			(5, _, _, 0) => skip_if!(self.regs[x] == self.regs[y]),
+			Op::SkipEq(x, y) => skip_if!(self.regs[x] == self.regs[y]),
 			// Skip if `Vx != Vy`
-			(9, _, _, 0) => skip_if!(self.regs[x] != self.regs[y]),
+			Op::SkipNe(x, y) => skip_if!(self.regs[x] != self.regs[y]),
+			// Store `Vx..=Vy` to `I..`
+			Op::StoreRange(x, y) => self.store_range(x, y),
+			// Read from `I..` to `Vx..=Vy`
+			Op::ReadRange(x, y) => self.read_range(x, y),
 
 			// Wait for a keypress
-			(0xf, _, 0, 0xa) => self.wait_for_keypress = Some(x),
+			Op::WaitKey(x) => self.wait_for_keypress = Some(x),
 			// Skip if `Vx == pressed key`
-			(0xe, _, 9, 0xe) => skip_if!(self.is_key_pressed(self.regs[x])),
+			Op::SkipKey(x) => skip_if!(self.is_key_pressed(self.regs[x])),
 			// Skip if `Vx != pressed key`
-			(0xe, _, 0xa, 1) => skip_if!(!self.is_key_pressed(self.regs[x])),
+			Op::SkipNotKey(x) => skip_if!(!self.is_key_pressed(self.regs[x])),
 
 			// `Vx = byte`
-			(6, _, _, _) => self.regs[x] = byte,
+			Op::SetByte(x, byte) => self.regs[x] = byte,
 			// `Vx = Vx + byte`
-			(7, _, _, _) => self.regs[x] = self.regs[x].wrapping_add(byte),
+			Op::AddByte(x, byte) => self.regs[x] = self.regs[x].wrapping_add(byte),
 			// `Vx = Vy`
-			(8, _, _, 0) => self.regs[x] = self.regs[y],
+			Op::Set(x, y) => self.regs[x] = self.regs[y],
 			// `Vx = Vx + Vy`
-			(8, _, _, 4) => self.add_vx_vy(x, y),
+			Op::Add(x, y) => self.add_vx_vy(x, y),
 			// `Vx = Vx - Vy`
-			(8, _, _, 5) => self.sub_vx_vy(x, y),
+			Op::Sub(x, y) => self.sub_vx_vy(x, y),
 			// `Vx = random & byte`
-			(0xc, _, _, _) => self.set_rand(x, byte),
+			Op::Rand(x, byte) => self.set_rand(x, byte),
 
 			// `Vx = Vx | Vy`
-			(8, _, _, 1) => self.regs[x] |= self.regs[y],
+			Op::Or(x, y) => self.logic_vx_vy(x, y, |vx, vy| vx | vy),
 			// `Vx = Vx & Vy`
-			(8, _, _, 2) => self.regs[x] &= self.regs[y],
+			Op::And(x, y) => self.logic_vx_vy(x, y, |vx, vy| vx & vy),
 			// `Vx = Vx ^ Vy`
-			(8, _, _, 3) => self.regs[x] ^= self.regs[y],
+			Op::Xor(x, y) => self.logic_vx_vy(x, y, |vx, vy| vx ^ vy),
 
 			// `Vx = Vx >> 1`
-			(8, _, _, 6) => self.shift_right(x),
+			Op::ShiftRight(x, y) => self.shift_right(x, y),
 			// `Vx = Vy - Vx`
-			(8, _, _, 7) => self.sub_vy_vx(y, x),
+			Op::SubN(x, y) => self.sub_vy_vx(x, y),
 			// `Vx = Vx << 1`
-			(8, _, _, 0xe) => self.shift_left(x),
+			Op::ShiftLeft(x, y) => self.shift_left(x, y),
 
 			// `Vx = DT`
-			(0xf, _, 0, 7) => self.regs[x] = self.delay_timer,
+			Op::GetDelay(x) => self.regs[x] = self.delay_timer,
 			// `DT = Vx`
-			(0xf, _, 1, 5) => self.delay_timer = self.regs[x],
+			Op::SetDelay(x) => self.delay_timer = self.regs[x],
 			// `ST = Vx`
-			(0xf, _, 1, 8) => self.sound_timer = self.regs[x],
+			Op::SetSound(x) => self.sound_timer = self.regs[x],
 
 			// `I = addr`
-			(0xa, _, _, _) => self.index = addr,
+			Op::SetIndex(addr) => self.index = addr,
+			// `I = next 16-bit word`
+			Op::LongIndex => {
+				self.index = self.next_word();
+				self.pc = self.pc.wrapping_add(2);
+			}
 			// `I = I + Vx`
-			(0xf, _, 1, 0xe) => self.inc_index(self.regs[x] as u16),
+			Op::AddIndex(x) => self.inc_index(self.regs[x] as u16),
 			// `I = Vx * 5`
-			(0xf, _, 2, 9) => self.set_index_sprite_addr(x),
+			Op::Font(x) => self.set_index_sprite_addr(x),
 			// Store Binary Coded Decimal (BCD) representation of `Vx`
-			(0xf, _, 3, 3) => self.store_bcd(x),
+			Op::Bcd(x) => self.store_bcd(x),
 			// Store `V0..=Vx` to `I..=I+x`
-			(0xf, _, 5, 5) => self.store_through(x),
+			Op::Store(x) => self.store_through(x),
 			// Read from `I..=I+x` to `V0..=Vx`
-			(0xf, _, 6, 5) => self.read_through(x),
-
-			(0, _, _, _) => (/* no-op */),
-			_ => self.error = true,
+			Op::Read(x) => self.read_through(x),
+			// `I = big font char of Vx`
+			Op::BigFont(x) => self.set_index_big_sprite_addr(x),
+			// Store `V0..=Vx` to the RPL flags
+			Op::StoreRpl(x) => self.store_rpl(x),
+			// Read from the RPL flags to `V0..=Vx`
+			Op::ReadRpl(x) => self.read_rpl(x),
+			// Load the audio pattern from `I..I+16`
+			Op::Audio => self.load_audio_pattern(),
+			// `pitch = Vx`
+			Op::Pitch(x) => self.pitch = self.regs[x],
+
+			Op::Sys(_) => (/* no-op */),
+			Op::Unknown(op) => {
+				self.report(EmuError::UnknownOpcode {
+					pc: self.ins_addr(),
+					op,
+				});
+			}
 		}
 	}
 	/// Decrement timers
@@ -363,66 +596,232 @@ impl Emu {
 		self.pc = addr;
 	}
 
-	pub fn mem_get(&mut self, addr: u16) -> u8 {
-		self.memory[addr as usize]
+	/// Size of the memory available in the current mode
+	pub fn memory_size(&self) -> usize {
+		Self::mode_memory_size(self.mode)
+	}
+	/// Memory size available in the mode
+	pub fn mode_memory_size(mode: Mode) -> usize {
+		if mode >= Mode::XoChip {
+			Self::MEMORY_SIZE
+		} else {
+			Self::CLASSIC_MEMORY_SIZE
+		}
+	}
+	/// Max size of a program that fits into the memory of the mode
+	pub fn max_program_size(mode: Mode) -> usize {
+		Self::mode_memory_size(mode) - Self::PROGRAM_START_ADDR
+	}
+
+	/// Memory byte at the address wrapped around the memory of the current mode
+	pub fn mem_get(&self, addr: usize) -> u8 {
+		self.memory[addr % self.memory_size()]
+	}
+	/// Set memory byte at the address wrapped around the memory of the current mode
+	pub fn mem_set(&mut self, addr: usize, value: u8) {
+		self.memory[addr % self.memory_size()] = value;
 	}
 	pub fn inc_index(&mut self, addr: u16) {
-		self.index = (self.index + addr) % self.memory.len() as u16;
+		self.index = ((self.index as usize + addr as usize) % self.memory_size()) as u16;
+	}
+
+	/// 16-bit word right after the current instruction
+	fn next_word(&self) -> u16 {
+		let pc = self.pc as usize;
+		(self.mem_get(pc) as u16) << 8 | self.mem_get(pc + 1) as u16
+	}
+	/// Skip the next instruction, see [`Op::skip_len`]
+	fn skip(&mut self) {
+		let len = Op::skip_len(self.next_word(), self.mode);
+		self.pc = self.pc.wrapping_add(len);
 	}
 
 	/// Push current program counter to the stack
-	pub fn stack_push(&mut self) {
-		self.stack[self.sp as usize] = self.pc;
-		self.sp += 1;
+	/// Returns `false` if the stack is full in the strict mode
+	pub fn stack_push(&mut self) -> bool {
+		if self.sp as usize >= Self::STACK_SIZE {
+			if self.report(EmuError::StackOverflow {
+				pc: self.ins_addr(),
+			}) {
+				return false;
+			}
 
-		// Wrap around on overflow because it's fun
-		if self.sp as usize >= self.stack.len() {
+			// Wrap around on overflow because it's fun
 			self.sp = 0;
-			self.error = true;
 		}
+
+		self.stack[self.sp as usize] = self.pc;
+		self.sp += 1;
+		true
 	}
 	/// Pop the last address in the stack
-	pub fn stack_pop(&mut self) -> u16 {
-		// Return random address on underflow because it's fun too
+	/// Returns `None` if the stack is empty in the strict mode
+	pub fn stack_pop(&mut self) -> Option<u16> {
 		if self.sp == 0 {
-			self.error = true;
-			let addr = (quad_rand::rand() % self.memory.len() as u32) as u16;
-			return addr;
+			if self.report(EmuError::StackUnderflow {
+				pc: self.ins_addr(),
+			}) {
+				return None;
+			}
+
+			// Return random address on underflow because it's fun too
+			let addr = (self.rng.next_u64() % self.memory_size() as u64) as u16;
+			return Some(addr);
 		}
 
 		self.sp -= 1;
-		self.stack[self.sp as usize]
+		Some(self.stack[self.sp as usize])
+	}
+
+	/// Log the error, in the strict mode also halt at the instruction which caused it
+	/// Returns `true` if the instruction has to be aborted
+	pub fn report(&mut self, error: EmuError) -> bool {
+		self.errors.push(error);
+
+		if self.strict {
+			self.pc = error.pc();
+			self.debugger.halt(Hit::Error(error));
+		}
+		self.strict
+	}
+	/// Check that `len` bytes at `I` fit into the memory according to the memory policy
+	/// Returns `false` if the access has to be skipped
+	fn check_index_access(&mut self, len: usize) -> bool {
+		let end = self.index as usize + len;
+		if end <= self.memory_size() || self.memory_policy == MemoryPolicy::Wrap {
+			return true;
+		}
+
+		self.report(EmuError::MemoryOutOfBounds {
+			pc: self.ins_addr(),
+			addr: self.index,
+		});
+		false
+	}
+	/// Address of the instruction being executed
+	fn ins_addr(&self) -> u16 {
+		self.pc.wrapping_sub(2)
+	}
+
+	/// Width of the current resolution
+	pub fn screen_width(&self) -> usize {
+		if self.hires {
+			Self::SCREEN_WIDTH
+		} else {
+			Self::LORES_WIDTH
+		}
+	}
+	/// Height of the current resolution
+	pub fn screen_height(&self) -> usize {
+		if self.hires {
+			Self::SCREEN_HEIGHT
+		} else {
+			Self::LORES_HEIGHT
+		}
+	}
+	/// Planes bitmask of the pixel at the specified position of the current resolution
+	pub fn pixel(&self, x: usize, y: usize) -> u8 {
+		self.screen[x + y * self.screen_width()]
+	}
+	/// Switch between low-res and hi-res modes, the screen is cleared
+	pub fn set_hires(&mut self, hires: bool) {
+		self.hires = hires;
+		self.screen.fill(0);
+	}
+	/// Clear the selected planes
+	pub fn screen_clear(&mut self) {
+		for pixel in self.screen.iter_mut() {
+			*pixel &= !self.planes;
+		}
 	}
 
 	pub fn screen_draw(&mut self, x: u8, y: u8, n: u8) {
-		let vx = self.regs[x] as usize;
-		let vy = self.regs[y] as usize;
+		let width = self.screen_width();
+		let height = self.screen_height();
+
+		// SUPER-CHIP draws 16x16 sprites when `n` is zero
+		let big = n == 0 && self.mode >= Mode::SuperChip;
+		let (cols, rows) = if big { (16, 16) } else { (8, n as usize) };
+		let sprite_len = if big { 32 } else { n as usize };
+
+		let planes_count = (self.planes & 0b11).count_ones() as usize;
+		if !self.check_index_access(sprite_len * planes_count) {
+			return;
+		}
+
+		// Sprite origin always wraps around, only the sprite itself may be clipped
+		let vx = self.regs[x] as usize % width;
+		let vy = self.regs[y] as usize % height;
+		let clip = self.quirks.clip_sprites;
 		let mut overlap = false;
 
-		for row in 0..n as usize {
-			let mut byte = self.mem_get(self.index + row as u16);
-			let py = (vy + row) % Self::SCREEN_HEIGHT;
+		// Sprite data of each selected plane follows the previous one
+		let mut addr = self.index as usize;
+		for plane in [1, 2] {
+			if self.planes & plane == 0 {
+				continue;
+			}
 
-			for col in 0..8 {
-				if byte & 0x80 > 0 {
-					let px = (vx + col) % Self::SCREEN_WIDTH;
-					let idx = px + py * Self::SCREEN_WIDTH;
+			for row in 0..rows {
+				if clip && vy + row >= height {
+					break;
+				}
 
-					if self.screen[idx] {
-						self.screen[idx] = false;
-						overlap = true;
-					} else {
-						self.screen[idx] = true;
+				let mut line = if big {
+					let addr = addr + row * 2;
+					(self.mem_get(addr) as u16) << 8 | self.mem_get(addr + 1) as u16
+				} else {
+					(self.mem_get(addr + row) as u16) << 8
+				};
+				let py = (vy + row) % height;
+
+				for col in 0..cols {
+					if clip && vx + col >= width {
+						break;
+					}
+
+					if line & 0x8000 > 0 {
+						let px = (vx + col) % width;
+						let pixel = &mut self.screen[px + py * width];
+
+						overlap |= *pixel & plane > 0;
+						*pixel ^= plane;
 					}
-				}
 
-				byte <<= 1;
+					line <<= 1;
+				}
 			}
+
+			addr += sprite_len;
 		}
 
 		self.regs[0xf] = overlap as u8;
 	}
 
+	/// Scroll the selected planes by the specified number of pixels
+	pub fn scroll(&mut self, dx: isize, dy: isize) {
+		let width = self.screen_width();
+		let height = self.screen_height();
+		let prev = self.screen;
+
+		for y in 0..height {
+			for x in 0..width {
+				let sx = x as isize - dx;
+				let sy = y as isize - dy;
+
+				let src = if (0..width as isize).contains(&sx) && (0..height as isize).contains(&sy)
+				{
+					prev[sx as usize + sy as usize * width]
+				} else {
+					0
+				};
+
+				let idx = x + y * width;
+				self.screen[idx] = (prev[idx] & !self.planes) | (src & self.planes);
+			}
+		}
+	}
+
 	/// `Vx += Vy; VF = overflow`
 	pub fn add_vx_vy(&mut self, x: u8, y: u8) {
 		let (byte, overflow) = self.regs[x].overflowing_add(self.regs[y]);
@@ -434,62 +833,186 @@ impl Emu {
 		let vx = self.regs[x];
 		let vy = self.regs[y];
 
-		self.regs[0xF] = (vx > vy) as u8;
 		self.regs[x] = vx.wrapping_sub(vy);
+		self.regs[0xF] = (vx >= vy) as u8;
 	}
 	/// `Vx = Vy - Vx; VF = NOT underflow`
 	pub fn sub_vy_vx(&mut self, x: u8, y: u8) {
 		let vx = self.regs[x];
 		let vy = self.regs[y];
 
-		self.regs[0xF] = (vy > vx) as u8;
 		self.regs[x] = vy.wrapping_sub(vx);
+		self.regs[0xF] = (vy >= vx) as u8;
 	}
 
-	/// Set `VF` to the least-significant bit of `Vx` and then divide `Vx` by 2
-	pub fn shift_right(&mut self, x: u8) {
-		self.regs[0xF] = self.regs[x] & 0x1;
-		self.regs[x] >>= 1;
+	/// `Vx = op(Vx, Vy)`, also reset `VF` with the VF reset quirk
+	pub fn logic_vx_vy(&mut self, x: u8, y: u8, op: fn(u8, u8) -> u8) {
+		self.regs[x] = op(self.regs[x], self.regs[y]);
+
+		if self.quirks.vf_reset {
+			self.regs[0xF] = 0;
+		}
 	}
-	/// Set `VF` to the most-significant bit of `Vx` and then multiply `Vx` by 2
-	pub fn shift_left(&mut self, x: u8) {
-		self.regs[0xF] = (self.regs[x] & 0x80) >> 7;
-		self.regs[x] <<= 1;
+
+	/// Value to be shifted by `8xy6`/`8xyE`, depends on the shift quirk
+	fn shift_source(&self, x: u8, y: u8) -> u8 {
+		if self.quirks.shift_vy {
+			self.regs[y]
+		} else {
+			self.regs[x]
+		}
+	}
+	/// Divide `Vx` by 2 and set `VF` to the shifted out least-significant bit
+	pub fn shift_right(&mut self, x: u8, y: u8) {
+		let value = self.shift_source(x, y);
+		self.regs[x] = value >> 1;
+		self.regs[0xF] = value & 0x1;
+	}
+	/// Multiply `Vx` by 2 and set `VF` to the shifted out most-significant bit
+	pub fn shift_left(&mut self, x: u8, y: u8) {
+		let value = self.shift_source(x, y);
+		self.regs[x] = value << 1;
+		self.regs[0xF] = (value & 0x80) >> 7;
 	}
 
 	/// `Vx = random value & byte`
 	pub fn set_rand(&mut self, x: u8, byte: u8) {
-		self.regs[x] = (quad_rand::rand() % 255) as u8 & byte
+		self.regs[x] = self.rng.next_u8() & byte
 	}
 
 	/// Store hundreds, tens and ones of `Vx` at `I`, `I+1` and `I+3` respectively
 	#[allow(clippy::identity_op)]
 	pub fn store_bcd(&mut self, x: u8) {
+		if !self.check_index_access(3) {
+			return;
+		}
+
 		let vx = self.regs[x];
 		let i = self.index as usize;
 
-		self.memory[i + 0] = vx / 100; // Hundreds
-		self.memory[i + 1] = (vx % 100) / 10; // Tens
-		self.memory[i + 2] = vx % 10; // Ones
+		self.mem_set(i + 0, vx / 100); // Hundreds
+		self.mem_set(i + 1, (vx % 100) / 10); // Tens
+		self.mem_set(i + 2, vx % 10); // Ones
+
+		self.watch_write(i, 3);
 	}
 	/// Store values of `V0..=Vx` to `I..=I+x`
 	pub fn store_through(&mut self, x: u8) {
+		if !self.check_index_access(x as usize + 1) {
+			return;
+		}
+
 		let i = self.index as usize;
 		for v in 0..=x {
-			self.memory[i + v as usize] = self.regs[v];
+			self.mem_set(i + v as usize, self.regs[v]);
 		}
+
+		self.watch_write(i, x as usize + 1);
+		self.load_store_index(x);
 	}
 	/// Read values from `I..=I+x` to `V0..=Vx`
 	pub fn read_through(&mut self, x: u8) {
+		if !self.check_index_access(x as usize + 1) {
+			return;
+		}
+
 		let i = self.index as usize;
 		for v in 0..=x {
-			self.regs[v] = self.memory[i + v as usize];
+			self.regs[v] = self.mem_get(i + v as usize);
+		}
+
+		self.load_store_index(x);
+	}
+	/// Let the debugger check the memory written by the current instruction
+	fn watch_write(&mut self, addr: usize, len: usize) {
+		self.debugger
+			.check_write(self.ins_addr(), addr as u16, len as u16);
+	}
+	/// Move `I` after `Fx55`/`Fx65` according to the load/store quirk
+	fn load_store_index(&mut self, x: u8) {
+		match self.quirks.load_store {
+			LoadStore::Increment => self.inc_index(x as u16 + 1),
+			LoadStore::IncrementX => self.inc_index(x as u16),
+			LoadStore::Keep => (),
+		}
+	}
+	/// Store values of `Vx..=Vy` to `I..`, registers can go in both directions
+	pub fn store_range(&mut self, x: u8, y: u8) {
+		if !self.check_index_access(x.abs_diff(y) as usize + 1) {
+			return;
+		}
+
+		let i = self.index as usize;
+		for (offset, v) in Self::reg_range(x, y).enumerate() {
+			self.mem_set(i + offset, self.regs[v]);
+		}
+
+		self.watch_write(i, x.abs_diff(y) as usize + 1);
+	}
+	/// Read values from `I..` to `Vx..=Vy`, registers can go in both directions
+	pub fn read_range(&mut self, x: u8, y: u8) {
+		if !self.check_index_access(x.abs_diff(y) as usize + 1) {
+			return;
 		}
+
+		let i = self.index as usize;
+		for (offset, v) in Self::reg_range(x, y).enumerate() {
+			self.regs[v] = self.mem_get(i + offset);
+		}
+	}
+	fn reg_range(x: u8, y: u8) -> impl Iterator<Item = u8> {
+		let len = x.abs_diff(y) + 1;
+		(0..len).map(move |i| if x <= y { x + i } else { x - i })
 	}
 	/// Set `I` to the address of the `Vx`'th sprite
 	pub fn set_index_sprite_addr(&mut self, x: u8) {
 		self.index = self.regs[x] as u16 * Self::CHAR_HEIGHT;
 	}
+	/// Set `I` to the address of the `Vx`'th big font sprite
+	pub fn set_index_big_sprite_addr(&mut self, x: u8) {
+		let char = (self.regs[x] & 0xF) as u16;
+		self.index = Self::BIG_FONT_ADDR + char * Self::BIG_CHAR_HEIGHT;
+	}
+
+	/// Store values of `V0..=Vx` to the RPL flags
+	pub fn store_rpl(&mut self, x: u8) {
+		for v in 0..=x {
+			self.rpl[v as usize] = self.regs[v];
+		}
+	}
+	/// Read values from the RPL flags to `V0..=Vx`
+	pub fn read_rpl(&mut self, x: u8) {
+		for v in 0..=x {
+			self.regs[v] = self.rpl[v as usize];
+		}
+	}
+
+	/// Copy 16 bytes at `I` into the audio pattern
+	pub fn load_audio_pattern(&mut self) {
+		if !self.check_index_access(Self::AUDIO_PATTERN_SIZE) {
+			return;
+		}
+
+		let mut pattern = [0; Self::AUDIO_PATTERN_SIZE];
+		for (offset, byte) in pattern.iter_mut().enumerate() {
+			*byte = self.mem_get(self.index as usize + offset);
+		}
+
+		self.audio_pattern = Some(pattern);
+	}
+	/// Audio pattern playback rate in Hz derived from the pitch
+	pub fn audio_pattern_rate(&self) -> f32 {
+		4000.0 * 2_f32.powf((self.pitch as f32 - 64.0) / 48.0)
+	}
+
+	/// Hash of the screen pixels, used to compare screens in tests
+	pub fn screen_hash(&self) -> u64 {
+		fnv1a(&self.screen)
+	}
+	/// Hash of the loaded program, used to match snapshots with ROMs
+	pub fn program_hash(&self) -> u64 {
+		fnv1a(&self.program)
+	}
 
 	/// Whether the CPU is hot
 	pub fn is_hot(&self) -> bool {
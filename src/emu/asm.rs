@@ -0,0 +1,866 @@
+use std::{
+	collections::{HashMap, VecDeque},
+	fmt,
+};
+
+use super::{Cmp, Emu, Op};
+
+/// Assembly error
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AsmError {
+	/// Line number starting from 1
+	pub line: usize,
+	pub msg: String,
+}
+impl fmt::Display for AsmError {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		write!(f, "line {}: {}", self.line, self.msg)
+	}
+}
+
+/// Assemble Octo-style source into a CHIP-8 program
+///
+/// Supported:
+/// - `: label`, `:const NAME value`, `:alias name vX`, `:macro name ARGS... { ... }`
+/// - `if ... then`, `if ... begin ... else ... end`, `loop ... while ... again`
+/// - `:call`, `:byte`, `:org` and raw numbers which are emitted as bytes
+pub fn assemble(source: &str) -> Result<Vec<u8>, AsmError> {
+	Assembler::new(source).run()
+}
+
+#[derive(Debug, Clone)]
+struct Token {
+	text: String,
+	line: usize,
+	/// Number of macro expansions the token came from
+	depth: usize,
+}
+
+/// Address that has to be patched once the label is defined
+struct Fixup {
+	/// Offset in the program
+	at: usize,
+	label: String,
+	line: usize,
+	/// Whether the whole 16-bit word is the address (`i := long`), otherwise only `nnn` is
+	long: bool,
+}
+
+/// Value of an operand
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Operand {
+	Reg(u8),
+	Num(u16),
+}
+
+/// Condition of `if` and `while`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Condition {
+	/// `vx key` or `vx -key`
+	Key { x: u8, pressed: bool },
+	/// `vx == ...`, `vx < ...` and other comparisons
+	Cmp { x: u8, cmp: Cmp, rhs: Operand },
+}
+
+/// Open structured control flow block
+enum Block {
+	/// Offset of the jump to patch at `else` or `end`
+	If { jump: usize },
+	/// Offset of the loop start and of the `while` jumps to patch at `again`
+	Loop { start: usize, breaks: Vec<usize> },
+}
+
+struct Macro {
+	args: Vec<String>,
+	body: Vec<Token>,
+}
+
+struct Assembler {
+	tokens: VecDeque<Token>,
+	line: usize,
+	/// Macro expansion depth of the current statement
+	depth: usize,
+
+	program: Vec<u8>,
+	/// Write offset in `program`
+	pc: usize,
+	labels: HashMap<String, u16>,
+	consts: HashMap<String, u16>,
+	aliases: HashMap<String, u8>,
+	macros: HashMap<String, Macro>,
+	fixups: Vec<Fixup>,
+	blocks: Vec<Block>,
+	/// Whether the `jump main` placeholder is still at the program start
+	has_main_jump: bool,
+}
+impl Assembler {
+	/// Macros can invoke other macros up to this depth, so recursive ones don't expand forever
+	const MAX_MACRO_DEPTH: usize = 16;
+
+	fn new(source: &str) -> Self {
+		let mut tokens = VecDeque::new();
+
+		for (i, line) in source.lines().enumerate() {
+			let line_text = line.split('#').next().unwrap_or_default();
+			for text in line_text.split_whitespace() {
+				tokens.push_back(Token {
+					text: text.to_string(),
+					line: i + 1,
+					depth: 0,
+				});
+			}
+		}
+
+		Self {
+			tokens,
+			line: 1,
+			depth: 0,
+
+			program: Vec::new(),
+			pc: 0,
+			labels: HashMap::new(),
+			consts: HashMap::new(),
+			aliases: HashMap::new(),
+			macros: HashMap::new(),
+			fixups: Vec::new(),
+			blocks: Vec::new(),
+			has_main_jump: false,
+		}
+	}
+
+	fn run(mut self) -> Result<Vec<u8>, AsmError> {
+		// Reserve `jump main`, it is dropped if `main` is defined right after it
+		self.emit_op(Op::Jump(0));
+		self.fixups.push(Fixup {
+			at: 0,
+			label: "main".to_string(),
+			line: 1,
+			long: false,
+		});
+		self.has_main_jump = true;
+
+		while let Some(token) = self.tokens.pop_front() {
+			self.line = token.line;
+			self.depth = token.depth;
+			self.statement(&token.text)?;
+		}
+
+		if let Some(block) = self.blocks.last() {
+			let msg = match block {
+				Block::If { .. } => "\"begin\" without \"end\"",
+				Block::Loop { .. } => "\"loop\" without \"again\"",
+			};
+			return Err(self.error(msg));
+		}
+
+		for fixup in std::mem::take(&mut self.fixups) {
+			let Some(addr) = self.labels.get(&fixup.label).copied() else {
+				self.line = fixup.line;
+				return Err(self.error(format!("undefined label \"{}\"", fixup.label)));
+			};
+
+			let old = u16::from_be_bytes([self.program[fixup.at], self.program[fixup.at + 1]]);
+			let new = if fixup.long {
+				addr
+			} else {
+				(old & 0xF000) | (addr & 0x0FFF)
+			};
+			self.program[fixup.at..fixup.at + 2].copy_from_slice(&new.to_be_bytes());
+		}
+
+		let max = Emu::PROGRAM_SIZE;
+		if self.program.len() > max {
+			return Err(self.error(format!("program is larger than {max} bytes")));
+		}
+
+		Ok(self.program)
+	}
+
+	fn statement(&mut self, token: &str) -> Result<(), AsmError> {
+		if let Some(mac) = self.macros.get(token) {
+			return self.expand(token, mac.args.len());
+		}
+
+		match token {
+			":" => {
+				let name = self.next()?;
+				self.define_label(name)?;
+			}
+			":const" => {
+				let name = self.next()?;
+				let value = self.number()?;
+				self.consts.insert(name, value);
+			}
+			":alias" => {
+				let name = self.next()?;
+				let reg = self.register()?;
+				self.aliases.insert(name, reg);
+			}
+			":macro" => self.define_macro()?,
+			":call" => {
+				let target = self.next()?;
+				self.emit_addr_op(Op::Call(0), &target)?;
+			}
+			":byte" => {
+				let value = self.number()?;
+				let byte = self.byte(value)?;
+				self.emit(&[byte]);
+			}
+			":org" => {
+				let addr = self.number()? as usize;
+				self.pc = addr
+					.checked_sub(Emu::PROGRAM_START_ADDR)
+					.ok_or_else(|| self.error("address is below the program start"))?;
+			}
+
+			"clear" => self.emit_op(Op::Clear),
+			"return" | ";" => self.emit_op(Op::Return),
+			"exit" => self.emit_op(Op::Exit),
+			"lores" => self.emit_op(Op::Lores),
+			"hires" => self.emit_op(Op::Hires),
+			"scroll-left" => self.emit_op(Op::ScrollLeft),
+			"scroll-right" => self.emit_op(Op::ScrollRight),
+			"scroll-down" => {
+				let n = self.nibble()?;
+				self.emit_op(Op::ScrollDown(n));
+			}
+			"scroll-up" => {
+				let n = self.nibble()?;
+				self.emit_op(Op::ScrollUp(n));
+			}
+			"audio" => self.emit_op(Op::Audio),
+
+			"jump" => {
+				let target = self.next()?;
+				self.emit_addr_op(Op::Jump(0), &target)?;
+			}
+			"jump0" => {
+				let target = self.next()?;
+				self.emit_addr_op(Op::JumpOffset(0, 0), &target)?;
+			}
+
+			"sprite" => {
+				let x = self.register()?;
+				let y = self.register()?;
+				let n = self.nibble()?;
+				self.emit_op(Op::Draw(x, y, n));
+			}
+			"bcd" => {
+				let x = self.register()?;
+				self.emit_op(Op::Bcd(x));
+			}
+			"save" | "load" => {
+				let x = self.register()?;
+				let range = self.peek() == Some("-");
+				let op = match (token, range) {
+					("save", false) => Op::Store(x),
+					("load", false) => Op::Read(x),
+					(_, true) => {
+						self.next()?;
+						let y = self.register()?;
+						if token == "save" {
+							Op::StoreRange(x, y)
+						} else {
+							Op::ReadRange(x, y)
+						}
+					}
+					_ => unreachable!(),
+				};
+				self.emit_op(op);
+			}
+			"saveflags" => {
+				let x = self.register()?;
+				self.emit_op(Op::StoreRpl(x));
+			}
+			"loadflags" => {
+				let x = self.register()?;
+				self.emit_op(Op::ReadRpl(x));
+			}
+			"plane" => {
+				let n = self.nibble()?;
+				self.emit_op(Op::Planes(n));
+			}
+
+			"delay" | "buzzer" | "pitch" => {
+				self.expect(":=")?;
+				let x = self.register()?;
+				let op = match token {
+					"delay" => Op::SetDelay(x),
+					"buzzer" => Op::SetSound(x),
+					_ => Op::Pitch(x),
+				};
+				self.emit_op(op);
+			}
+			"i" => self.index_statement()?,
+
+			"if" => self.if_statement()?,
+			"else" => {
+				let Some(Block::If { jump }) = self.blocks.pop() else {
+					return Err(self.error("\"else\" without \"begin\""));
+				};
+
+				let end_jump = self.pc;
+				self.emit_op(Op::Jump(0));
+				self.patch_jump(jump, self.pc);
+				self.blocks.push(Block::If { jump: end_jump });
+			}
+			"end" => {
+				let Some(Block::If { jump }) = self.blocks.pop() else {
+					return Err(self.error("\"end\" without \"begin\""));
+				};
+				self.patch_jump(jump, self.pc);
+			}
+			"loop" => self.blocks.push(Block::Loop {
+				start: self.pc,
+				breaks: Vec::new(),
+			}),
+			"while" => {
+				// Jump out of the loop if the condition is false
+				let cond = self.condition()?;
+				self.emit_condition(cond, true)?;
+
+				let jump = self.pc;
+				self.emit_op(Op::Jump(0));
+
+				let Some(Block::Loop { breaks, .. }) = self.blocks.last_mut() else {
+					return Err(self.error("\"while\" outside of a loop"));
+				};
+				breaks.push(jump);
+			}
+			"again" => {
+				let Some(Block::Loop { start, breaks }) = self.blocks.pop() else {
+					return Err(self.error("\"again\" without \"loop\""));
+				};
+
+				self.emit_op(Op::Jump(Self::addr(start)));
+				for jump in breaks {
+					self.patch_jump(jump, self.pc);
+				}
+			}
+
+			_ => {
+				if let Ok(x) = self.parse_register(token) {
+					return self.register_statement(x);
+				}
+				if let Ok(value) = self.parse_number(token) {
+					let byte = self.byte(value)?;
+					self.emit(&[byte]);
+					return Ok(());
+				}
+
+				// Anything else is a subroutine call
+				self.emit_addr_op(Op::Call(0), token)?;
+			}
+		}
+
+		Ok(())
+	}
+
+	/// `vx := ...`, `vx += ...` and other register operations
+	fn register_statement(&mut self, x: u8) -> Result<(), AsmError> {
+		let op = self.next()?;
+
+		match op.as_str() {
+			":=" => {
+				let src = self.next()?;
+				match src.as_str() {
+					"random" => {
+						let kk = self.number()?;
+						self.emit_op(Op::Rand(x, self.byte(kk)?));
+					}
+					"delay" => self.emit_op(Op::GetDelay(x)),
+					"key" => self.emit_op(Op::WaitKey(x)),
+					_ => match self.parse_operand(&src)? {
+						Operand::Reg(y) => self.emit_op(Op::Set(x, y)),
+						Operand::Num(kk) => self.emit_op(Op::SetByte(x, self.byte(kk)?)),
+					},
+				}
+			}
+			"+=" => match self.operand()? {
+				Operand::Reg(y) => self.emit_op(Op::Add(x, y)),
+				Operand::Num(kk) => self.emit_op(Op::AddByte(x, self.byte(kk)?)),
+			},
+			"-=" => match self.operand()? {
+				Operand::Reg(y) => self.emit_op(Op::Sub(x, y)),
+				Operand::Num(kk) => self.emit_op(Op::AddByte(x, self.byte(kk)?.wrapping_neg())),
+			},
+			"=-" | "|=" | "&=" | "^=" | ">>=" | "<<=" => {
+				let y = self.register()?;
+				let op = match op.as_str() {
+					"=-" => Op::SubN(x, y),
+					"|=" => Op::Or(x, y),
+					"&=" => Op::And(x, y),
+					"^=" => Op::Xor(x, y),
+					">>=" => Op::ShiftRight(x, y),
+					_ => Op::ShiftLeft(x, y),
+				};
+				self.emit_op(op);
+			}
+			_ => return Err(self.error(format!("unknown operator \"{op}\""))),
+		}
+
+		Ok(())
+	}
+
+	/// `i := ...` and `i += vx`
+	fn index_statement(&mut self) -> Result<(), AsmError> {
+		let op = self.next()?;
+
+		match op.as_str() {
+			"+=" => {
+				let x = self.register()?;
+				self.emit_op(Op::AddIndex(x));
+			}
+			":=" => {
+				let src = self.next()?;
+				match src.as_str() {
+					"hex" => {
+						let x = self.register()?;
+						self.emit_op(Op::Font(x));
+					}
+					"bighex" => {
+						let x = self.register()?;
+						self.emit_op(Op::BigFont(x));
+					}
+					"long" => {
+						let target = self.next()?;
+						self.emit_op(Op::LongIndex);
+						match self.parse_number(&target) {
+							Ok(addr) => self.emit(&addr.to_be_bytes()),
+							Err(_) => {
+								self.fixups.push(Fixup {
+									at: self.pc,
+									label: target,
+									line: self.line,
+									long: true,
+								});
+								self.emit(&[0, 0]);
+							}
+						}
+					}
+					_ => self.emit_addr_op(Op::SetIndex(0), &src)?,
+				}
+			}
+			_ => return Err(self.error(format!("unknown operator \"{op}\""))),
+		}
+
+		Ok(())
+	}
+
+	/// `if ... then` and `if ... begin`
+	fn if_statement(&mut self) -> Result<(), AsmError> {
+		let cond = self.condition()?;
+
+		match self.next()?.as_str() {
+			// Run the next instruction only if the condition is true
+			"then" => self.emit_condition(cond, false)?,
+			// Jump over the block if the condition is false
+			"begin" => {
+				self.emit_condition(cond, true)?;
+				self.blocks.push(Block::If { jump: self.pc });
+				self.emit_op(Op::Jump(0));
+			}
+			other => {
+				return Err(self.error(format!("expected \"then\" or \"begin\", got \"{other}\"")));
+			}
+		}
+
+		Ok(())
+	}
+
+	fn condition(&mut self) -> Result<Condition, AsmError> {
+		let x = self.register()?;
+		let cmp = self.next()?;
+
+		// Key conditions don't have a right operand
+		match cmp.as_str() {
+			"key" => return Ok(Condition::Key { x, pressed: true }),
+			"-key" => return Ok(Condition::Key { x, pressed: false }),
+			_ => (),
+		}
+
+		let cmp = Cmp::from_name(&cmp)
+			.ok_or_else(|| self.error(format!("unknown comparison \"{cmp}\"")))?;
+		let rhs = self.operand()?;
+		Ok(Condition::Cmp { x, cmp, rhs })
+	}
+	/// Emit instructions so the next one runs only if the condition is true,
+	/// or only if it is false when `invert` is set
+	fn emit_condition(&mut self, cond: Condition, invert: bool) -> Result<(), AsmError> {
+		let (x, cmp, rhs) = match cond {
+			Condition::Key { x, pressed } => {
+				let op = if pressed != invert {
+					Op::SkipNotKey(x)
+				} else {
+					Op::SkipKey(x)
+				};
+				self.emit_op(op);
+				return Ok(());
+			}
+			Condition::Cmp { x, cmp, rhs } => (x, cmp, rhs),
+		};
+
+		// Relational operators compare through `VF`, which holds `lhs >= rhs`
+		// after `vf := lhs; vf -= rhs`
+		let (cmp, x, rhs) = match cmp {
+			Cmp::Eq | Cmp::Ne => (cmp, x, rhs),
+			Cmp::Lt | Cmp::Ge => {
+				match rhs {
+					Operand::Reg(y) => {
+						self.emit_op(Op::Set(0xF, x));
+						self.emit_op(Op::Sub(0xF, y));
+					}
+					Operand::Num(kk) => {
+						self.emit_op(Op::SetByte(0xF, self.byte(kk)?));
+						self.emit_op(Op::SubN(0xF, x));
+					}
+				}
+				let flag = if cmp == Cmp::Lt { 0 } else { 1 };
+				(Cmp::Eq, 0xF, Operand::Num(flag))
+			}
+			Cmp::Gt | Cmp::Le => {
+				match rhs {
+					Operand::Reg(y) => self.emit_op(Op::Set(0xF, y)),
+					Operand::Num(kk) => self.emit_op(Op::SetByte(0xF, self.byte(kk)?)),
+				}
+				self.emit_op(Op::Sub(0xF, x));
+				let flag = if cmp == Cmp::Gt { 0 } else { 1 };
+				(Cmp::Eq, 0xF, Operand::Num(flag))
+			}
+		};
+
+		// Whether the next instruction runs only if the operands are equal
+		let equal = (cmp == Cmp::Eq) != invert;
+		let op = match (rhs, equal) {
+			(Operand::Num(kk), true) => Op::SkipNeByte(x, self.byte(kk)?),
+			(Operand::Num(kk), false) => Op::SkipEqByte(x, self.byte(kk)?),
+			(Operand::Reg(y), true) => Op::SkipNe(x, y),
+			(Operand::Reg(y), false) => Op::SkipEq(x, y),
+		};
+		self.emit_op(op);
+		Ok(())
+	}
+
+	fn define_label(&mut self, name: String) -> Result<(), AsmError> {
+		// Drop `jump main` if `main` starts right after it
+		if name == "main" && self.has_main_jump && self.pc == 2 {
+			self.program.clear();
+			self.pc = 0;
+			self.fixups.retain(|fixup| fixup.at != 0);
+			self.has_main_jump = false;
+		}
+
+		if self.labels.contains_key(&name) {
+			return Err(self.error(format!("label \"{name}\" is already defined")));
+		}
+
+		self.labels.insert(name, Self::addr(self.pc));
+		Ok(())
+	}
+
+	fn define_macro(&mut self) -> Result<(), AsmError> {
+		let name = self.next()?;
+
+		let mut args = Vec::new();
+		loop {
+			let token = self.next()?;
+			if token == "{" {
+				break;
+			}
+			args.push(token);
+		}
+
+		let mut body = Vec::new();
+		let mut depth = 1;
+		loop {
+			let token = self
+				.tokens
+				.pop_front()
+				.ok_or_else(|| self.error(format!("macro \"{name}\" is not closed")))?;
+
+			match token.text.as_str() {
+				"{" => depth += 1,
+				"}" => depth -= 1,
+				_ => (),
+			}
+			if depth == 0 {
+				break;
+			}
+
+			body.push(token);
+		}
+
+		self.macros.insert(name, Macro { args, body });
+		Ok(())
+	}
+
+	/// Replace the macro invocation with its body
+	fn expand(&mut self, name: &str, args_count: usize) -> Result<(), AsmError> {
+		if self.depth >= Self::MAX_MACRO_DEPTH {
+			return Err(self.error(format!("macro \"{name}\" expansion is too deep")));
+		}
+		let depth = self.depth + 1;
+
+		let mut values = Vec::with_capacity(args_count);
+		for _ in 0..args_count {
+			values.push(self.next()?);
+		}
+
+		let mac = &self.macros[name];
+		let line = self.line;
+
+		for token in mac.body.iter().rev() {
+			let text = match mac.args.iter().position(|arg| *arg == token.text) {
+				Some(i) => values[i].clone(),
+				None => token.text.clone(),
+			};
+			self.tokens.push_front(Token { text, line, depth });
+		}
+
+		Ok(())
+	}
+
+	fn next(&mut self) -> Result<String, AsmError> {
+		match self.tokens.pop_front() {
+			Some(token) => {
+				self.line = token.line;
+				Ok(token.text)
+			}
+			None => Err(self.error("unexpected end of file")),
+		}
+	}
+	fn peek(&self) -> Option<&str> {
+		self.tokens.front().map(|token| token.text.as_str())
+	}
+	fn expect(&mut self, expected: &str) -> Result<(), AsmError> {
+		let token = self.next()?;
+		if token != expected {
+			return Err(self.error(format!("expected \"{expected}\", got \"{token}\"")));
+		}
+		Ok(())
+	}
+
+	fn register(&mut self) -> Result<u8, AsmError> {
+		let token = self.next()?;
+		self.parse_register(&token)
+	}
+	fn parse_register(&self, token: &str) -> Result<u8, AsmError> {
+		if let Some(reg) = self.aliases.get(token) {
+			return Ok(*reg);
+		}
+
+		let mut chars = token.chars();
+		if let (Some('v' | 'V'), Some(c), None) = (chars.next(), chars.next(), chars.next())
+			&& let Some(reg) = c.to_digit(16)
+		{
+			return Ok(reg as u8);
+		}
+
+		Err(self.error(format!("expected a register, got \"{token}\"")))
+	}
+
+	fn number(&mut self) -> Result<u16, AsmError> {
+		let token = self.next()?;
+		self.parse_number(&token)
+	}
+	fn nibble(&mut self) -> Result<u8, AsmError> {
+		let n = self.number()?;
+		if n > 0xF {
+			return Err(self.error(format!("{n} doesn't fit into a nibble")));
+		}
+		Ok(n as u8)
+	}
+	/// Value of a byte operand or a raw data byte
+	fn byte(&self, value: u16) -> Result<u8, AsmError> {
+		u8::try_from(value).map_err(|_| self.error(format!("{value} doesn't fit into a byte")))
+	}
+	fn parse_number(&self, token: &str) -> Result<u16, AsmError> {
+		if let Some(value) = self.consts.get(token) {
+			return Ok(*value);
+		}
+
+		let (negative, digits) = match token.strip_prefix('-') {
+			Some(digits) => (true, digits),
+			None => (false, token),
+		};
+
+		let value = if let Some(hex) = digits.strip_prefix("0x") {
+			i32::from_str_radix(hex, 16)
+		} else if let Some(bin) = digits.strip_prefix("0b") {
+			i32::from_str_radix(bin, 2)
+		} else {
+			digits.parse()
+		};
+
+		match value {
+			Ok(value) if value <= u16::MAX as i32 => {
+				let value = if negative { -value } else { value };
+				// Negative numbers are stored as two's complement bytes, e.g. `-1` is `0xFF`
+				if value < i8::MIN as i32 {
+					Err(self.error(format!("{value} doesn't fit into a byte")))
+				} else if value < 0 {
+					Ok((value & 0xFF) as u16)
+				} else {
+					Ok(value as u16)
+				}
+			}
+			_ => Err(self.error(format!("expected a number, got \"{token}\""))),
+		}
+	}
+
+	fn operand(&mut self) -> Result<Operand, AsmError> {
+		let token = self.next()?;
+		self.parse_operand(&token)
+	}
+	fn parse_operand(&self, token: &str) -> Result<Operand, AsmError> {
+		if let Ok(reg) = self.parse_register(token) {
+			return Ok(Operand::Reg(reg));
+		}
+		self.parse_number(token).map(Operand::Num)
+	}
+
+	fn emit(&mut self, bytes: &[u8]) {
+		let end = self.pc + bytes.len();
+		if self.program.len() < end {
+			self.program.resize(end, 0);
+		}
+
+		self.program[self.pc..end].copy_from_slice(bytes);
+		self.pc = end;
+	}
+	fn emit_op(&mut self, op: Op) {
+		self.emit(&op.encode().to_be_bytes());
+	}
+	/// Emit the instruction with `nnn` set to the address of the number, constant or label
+	fn emit_addr_op(&mut self, op: Op, target: &str) -> Result<(), AsmError> {
+		let addr = match self.parse_number(target) {
+			Ok(addr) => addr,
+			Err(_) => {
+				if !Self::is_name(target) {
+					return Err(self.error(format!("expected an address, got \"{target}\"")));
+				}
+
+				self.fixups.push(Fixup {
+					at: self.pc,
+					label: target.to_string(),
+					line: self.line,
+					long: false,
+				});
+				0
+			}
+		};
+
+		if addr > 0xFFF {
+			return Err(self.error(format!("{addr:#X} doesn't fit into an address")));
+		}
+
+		self.emit(&(op.encode() | addr).to_be_bytes());
+		Ok(())
+	}
+	/// Point the `jump` at the offset to the target offset
+	fn patch_jump(&mut self, at: usize, target: usize) {
+		let ins = Op::Jump(Self::addr(target)).encode();
+		self.program[at..at + 2].copy_from_slice(&ins.to_be_bytes());
+	}
+
+	fn addr(offset: usize) -> u16 {
+		(offset + Emu::PROGRAM_START_ADDR) as u16
+	}
+	fn is_name(token: &str) -> bool {
+		token
+			.chars()
+			.all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '-')
+	}
+
+	fn error(&self, msg: impl Into<String>) -> AsmError {
+		AsmError {
+			line: self.line,
+			msg: msg.into(),
+		}
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	fn assert_assembles(source: &str, expected: &[u8]) {
+		let program = assemble(source).unwrap_or_else(|e| panic!("{e}"));
+		assert_eq!(program, expected);
+	}
+
+	#[test]
+	fn bundled_sources() {
+		assert_assembles(
+			include_str!("../../roms/src/tracer.8o"),
+			include_bytes!("../../roms/tracer.ch8"),
+		);
+		assert_assembles(
+			include_str!("../../roms/src/langtons-ant.8o"),
+			include_bytes!("../../roms/langtons-ant.ch8"),
+		);
+	}
+
+	#[test]
+	fn conditions() {
+		assert_assembles(
+			": main if v1 < v2 then clear",
+			&[0x8F, 0x10, 0x8F, 0x25, 0x4F, 0x00, 0x00, 0xE0],
+		);
+		assert_assembles(
+			": main loop while v0 != 3 again",
+			&[0x40, 0x03, 0x12, 0x06, 0x12, 0x00],
+		);
+		assert_assembles(
+			": main if v1 < v2 begin clear end",
+			&[0x8F, 0x10, 0x8F, 0x25, 0x3F, 0x00, 0x12, 0x0A, 0x00, 0xE0],
+		);
+		assert_assembles(
+			": main if v3 -key begin clear end",
+			&[0xE3, 0xA1, 0x12, 0x06, 0x00, 0xE0],
+		);
+	}
+
+	#[test]
+	fn errors() {
+		assert_eq!(assemble(": main\njump nowhere").unwrap_err().line, 2);
+		assert_eq!(assemble(": main\nloop").unwrap_err().line, 2);
+		assert_eq!(assemble(": main\nv0 := v16").unwrap_err().line, 2);
+
+		let e = assemble(":macro m { m }\nm").unwrap_err();
+		assert_eq!(e.msg, "macro \"m\" expansion is too deep");
+		assert_eq!(e.line, 2);
+
+		assert_eq!(
+			assemble(": main\n:byte 300").unwrap_err().msg,
+			"300 doesn't fit into a byte"
+		);
+		assert!(assemble(": main\n0x1234").is_err());
+		assert_assembles(": main\n:byte 255 -1 0x12", &[0xFF, 0xFF, 0x12]);
+
+		for source in [
+			"v0 := 300",
+			"v0 := random 0x1FF",
+			"v1 += 0x100",
+			"v1 -= 256",
+			"if v0 == 256 then clear",
+			"if v0 > 300 then clear",
+		] {
+			let e = assemble(&format!(": main\n{source}")).unwrap_err();
+			assert!(
+				e.msg.ends_with("doesn't fit into a byte"),
+				"{source}: {}",
+				e.msg
+			);
+		}
+		assert_eq!(
+			assemble(": main\nv0 := -129").unwrap_err().msg,
+			"-129 doesn't fit into a byte"
+		);
+		assert_assembles(": main\nv0 := -128", &[0x60, 0x80]);
+		assert_eq!(
+			assemble(": main\njump 0x1000").unwrap_err().msg,
+			"0x1000 doesn't fit into an address"
+		);
+	}
+}
@@ -0,0 +1,280 @@
+use std::{fmt, ops::RangeInclusive};
+
+use super::{EmuError, Registers};
+
+/// Register comparison operator
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Cmp {
+	Eq,
+	Ne,
+	Lt,
+	Le,
+	Gt,
+	Ge,
+}
+impl Cmp {
+	pub fn from_name(name: &str) -> Option<Self> {
+		match name {
+			"==" => Some(Self::Eq),
+			"!=" => Some(Self::Ne),
+			"<" => Some(Self::Lt),
+			"<=" => Some(Self::Le),
+			">" => Some(Self::Gt),
+			">=" => Some(Self::Ge),
+			_ => None,
+		}
+	}
+	pub fn name(self) -> &'static str {
+		match self {
+			Self::Eq => "==",
+			Self::Ne => "!=",
+			Self::Lt => "<",
+			Self::Le => "<=",
+			Self::Gt => ">",
+			Self::Ge => ">=",
+		}
+	}
+	pub fn test(self, a: u8, b: u8) -> bool {
+		match self {
+			Self::Eq => a == b,
+			Self::Ne => a != b,
+			Self::Lt => a < b,
+			Self::Le => a <= b,
+			Self::Gt => a > b,
+			Self::Ge => a >= b,
+		}
+	}
+}
+
+/// Breakpoint condition on a register value, e.g. `v3 == 0x10`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Condition {
+	pub reg: u8,
+	pub cmp: Cmp,
+	pub value: u8,
+}
+impl Condition {
+	/// Parse a condition like `v3 == 0x10`
+	pub fn parse(s: &str) -> Option<Self> {
+		let mut parts = s.split_whitespace();
+		let reg = parts.next()?.strip_prefix(['v', 'V'])?;
+		let reg = u8::from_str_radix(reg, 16).ok().filter(|r| *r < 16)?;
+		let cmp = Cmp::from_name(parts.next()?)?;
+		let value = parse_number(parts.next()?)?.try_into().ok()?;
+
+		if parts.next().is_some() {
+			return None;
+		}
+		Some(Self { reg, cmp, value })
+	}
+
+	pub fn test(&self, regs: &Registers) -> bool {
+		self.cmp.test(regs[self.reg], self.value)
+	}
+}
+impl fmt::Display for Condition {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		write!(
+			f,
+			"v{:x} {} 0x{:02X}",
+			self.reg,
+			self.cmp.name(),
+			self.value
+		)
+	}
+}
+
+/// Halts the execution before the instruction is executed
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Breakpoint {
+	/// Address of the instruction, `None` to break at any address
+	pub addr: Option<u16>,
+	/// Break only if the condition is met
+	pub cond: Option<Condition>,
+}
+impl Breakpoint {
+	/// Parse a breakpoint like `0x234`, `0x234 if v3 == 0x10` or `if v3 == 0x10`
+	pub fn parse(s: &str) -> Option<Self> {
+		let s = s.trim();
+		let (addr, cond) = match s.split_once("if") {
+			Some((addr, cond)) => (addr.trim(), Some(Condition::parse(cond)?)),
+			None => (s, None),
+		};
+		let addr = match addr {
+			"" => None,
+			addr => Some(parse_number(addr)?),
+		};
+
+		if addr.is_none() && cond.is_none() {
+			return None;
+		}
+		Some(Self { addr, cond })
+	}
+
+	pub fn test(&self, pc: u16, regs: &Registers) -> bool {
+		self.addr.is_none_or(|addr| addr == pc) && self.cond.is_none_or(|cond| cond.test(regs))
+	}
+}
+impl fmt::Display for Breakpoint {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		match (self.addr, self.cond) {
+			(Some(addr), Some(cond)) => write!(f, "0x{addr:04X} if {cond}"),
+			(Some(addr), None) => write!(f, "0x{addr:04X}"),
+			(None, Some(cond)) => write!(f, "if {cond}"),
+			(None, None) => write!(f, "always"),
+		}
+	}
+}
+
+/// Parse a memory range like `0x300` or `0x300-0x30F`
+pub fn parse_addr_range(s: &str) -> Option<RangeInclusive<u16>> {
+	let (start, end) = match s.split_once('-') {
+		Some((start, end)) => (parse_number(start.trim())?, parse_number(end.trim())?),
+		None => {
+			let addr = parse_number(s.trim())?;
+			(addr, addr)
+		}
+	};
+
+	(start <= end).then_some(start..=end)
+}
+
+/// Parse a decimal or `0x` prefixed hex number
+pub(crate) fn parse_number(s: &str) -> Option<u16> {
+	match s.strip_prefix("0x") {
+		Some(hex) => u16::from_str_radix(hex, 16).ok(),
+		None => s.parse().ok(),
+	}
+}
+
+/// Why the execution was halted
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Hit {
+	/// Breakpoint at the instruction address
+	Breakpoint { pc: u16 },
+	/// Instruction at `pc` has written into the watched memory at `addr`
+	Watchpoint { pc: u16, addr: u16 },
+	/// Error in the strict mode
+	Error(EmuError),
+}
+impl fmt::Display for Hit {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		match self {
+			Self::Breakpoint { pc } => write!(f, "Breakpoint at 0x{pc:04X}"),
+			Self::Watchpoint { pc, addr } => {
+				write!(f, "Write to 0x{addr:04X} at 0x{pc:04X}")
+			}
+			Self::Error(e) => write!(f, "{e}"),
+		}
+	}
+}
+
+/// Pauses the emulator on breakpoints and memory writes
+/// Breakpoints and watchpoints survive resets
+#[derive(Debug, Default, Clone)]
+pub struct Debugger {
+	/// Whether the execution is halted
+	pub paused: bool,
+	/// Reason of the last halt, `None` if paused by hand
+	pub hit: Option<Hit>,
+	pub breakpoints: Vec<Breakpoint>,
+	/// Memory ranges to break on when written by `Fx33`, `Fx55` or `5xy2`
+	pub watchpoints: Vec<RangeInclusive<u16>>,
+
+	/// Don't break on the next instruction, so resuming at a breakpoint doesn't hit it again
+	skip_break: bool,
+}
+impl Debugger {
+	/// Copy of the debugger with the same breakpoints and watchpoints, but running
+	pub fn restarted(&self) -> Self {
+		Self {
+			breakpoints: self.breakpoints.clone(),
+			watchpoints: self.watchpoints.clone(),
+			..Default::default()
+		}
+	}
+
+	pub fn pause(&mut self) {
+		self.paused = true;
+	}
+	pub fn resume(&mut self) {
+		self.paused = false;
+		self.hit = None;
+		self.skip_break = true;
+	}
+	pub fn toggle_pause(&mut self) {
+		if self.paused {
+			self.resume();
+		} else {
+			self.pause();
+		}
+	}
+
+	/// Add a breakpoint at the address or remove it if it already exists
+	/// Returns whether the breakpoint was added
+	pub fn toggle_breakpoint(&mut self, addr: u16) -> bool {
+		let bp = Breakpoint {
+			addr: Some(addr),
+			cond: None,
+		};
+
+		match self.breakpoints.iter().position(|b| *b == bp) {
+			Some(i) => {
+				self.breakpoints.remove(i);
+				false
+			}
+			None => {
+				self.breakpoints.push(bp);
+				true
+			}
+		}
+	}
+	/// Whether there is any breakpoint at the address
+	pub fn has_breakpoint(&self, addr: u16) -> bool {
+		self.breakpoints.iter().any(|b| b.addr == Some(addr))
+	}
+
+	/// Check breakpoints before executing the instruction at `pc`
+	pub fn check_breakpoints(&mut self, pc: u16, regs: &Registers) -> bool {
+		if std::mem::take(&mut self.skip_break) {
+			return false;
+		}
+
+		if self.breakpoints.iter().any(|b| b.test(pc, regs)) {
+			self.halt(Hit::Breakpoint { pc });
+			return true;
+		}
+
+		false
+	}
+	/// Check watchpoints after the instruction at `pc` has written `len` bytes at `addr`
+	pub fn check_write(&mut self, pc: u16, addr: u16, len: u16) {
+		let end = addr.saturating_add(len.saturating_sub(1));
+		let hit = self
+			.watchpoints
+			.iter()
+			.find(|range| addr <= *range.end() && *range.start() <= end);
+
+		if let Some(range) = hit {
+			let addr = addr.max(*range.start());
+			self.halt(Hit::Watchpoint { pc, addr });
+		}
+	}
+
+	/// Let the emulator run a single step while paused
+	pub(super) fn begin_step(&mut self) {
+		self.paused = false;
+		self.hit = None;
+		self.skip_break = true;
+	}
+	/// Pause again after the step unless it has hit something
+	pub(super) fn end_step(&mut self) {
+		self.skip_break = false;
+		self.paused = true;
+	}
+
+	pub(super) fn halt(&mut self, hit: Hit) {
+		self.paused = true;
+		self.hit = Some(hit);
+	}
+}
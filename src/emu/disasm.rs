@@ -0,0 +1,185 @@
+use std::fmt;
+
+use super::{Emu, Mode, Op};
+
+/// Max number of data bytes in a single line
+const DATA_LINE_LEN: usize = 8;
+
+/// What a line of the listing contains
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LineKind {
+	/// Instruction reachable from the program start
+	Code,
+	/// Data referenced by `Annn` or drawn by `Dxyn`
+	Data,
+	/// Bytes never reached by the program flow
+	Unknown,
+}
+
+/// Disassembled line
+#[derive(Debug, Clone)]
+pub struct Line {
+	pub addr: u16,
+	/// Number of bytes the line covers
+	pub len: u16,
+	pub kind: LineKind,
+	pub text: String,
+}
+impl fmt::Display for Line {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		match self.kind {
+			LineKind::Data => write!(f, "{:04X}  {}  # data", self.addr, self.text),
+			_ => write!(f, "{:04X}  {}", self.addr, self.text),
+		}
+	}
+}
+
+/// Disassemble the program into Octo-style mnemonics
+/// `program` is expected to start at [`Emu::PROGRAM_START_ADDR`], trailing zeros are dropped
+pub fn disassemble(program: &[u8], mode: Mode) -> Vec<Line> {
+	let len = program.iter().rposition(|b| *b != 0).map_or(0, |i| i + 1);
+	let program = &program[..len];
+
+	let kinds = trace(program, mode);
+	let start = Emu::PROGRAM_START_ADDR;
+
+	let mut lines = Vec::new();
+	let mut i = 0;
+
+	while i < program.len() {
+		let addr = (start + i) as u16;
+
+		if kinds[i] == Some(LineKind::Code) {
+			let op = Op::decode(word(program, i), mode);
+			let text = match op {
+				Op::LongIndex => format!("{op} 0x{:04X}", word(program, i + 2)),
+				op => op.to_string(),
+			};
+
+			let size = op.size() as usize;
+			lines.push(Line {
+				addr,
+				len: size as u16,
+				kind: LineKind::Code,
+				text,
+			});
+
+			i += size;
+			continue;
+		}
+
+		// Group bytes of the same kind until the next instruction
+		let kind = kinds[i].unwrap_or(LineKind::Unknown);
+		let mut end = i + 1;
+		while end < program.len()
+			&& end - i < DATA_LINE_LEN
+			&& kinds[end].unwrap_or(LineKind::Unknown) == kind
+		{
+			end += 1;
+		}
+
+		let text = program[i..end]
+			.iter()
+			.map(|b| format!("0x{b:02X}"))
+			.collect::<Vec<_>>()
+			.join(" ");
+
+		lines.push(Line {
+			addr,
+			len: (end - i) as u16,
+			kind,
+			text,
+		});
+		i = end;
+	}
+
+	lines
+}
+
+/// Follow every reachable path of the program and mark what each byte is
+/// `Some(Code)` is set only for the first byte of an instruction
+fn trace(program: &[u8], mode: Mode) -> Vec<Option<LineKind>> {
+	let start = Emu::PROGRAM_START_ADDR;
+	let mut kinds = vec![None; program.len()];
+	let mut visited = vec![false; program.len()];
+
+	// Offset of the next instruction and the known value of `I`
+	let mut queue = vec![(0_usize, None::<usize>)];
+
+	while let Some((mut i, mut index)) = queue.pop() {
+		while i + 1 < program.len() && !visited[i] {
+			visited[i] = true;
+
+			let op = Op::decode(word(program, i), mode);
+			let next = i + op.size() as usize;
+
+			if let Op::Unknown(_) = op {
+				break;
+			}
+			kinds[i] = Some(LineKind::Code);
+
+			let target = |addr: u16| (addr as usize).checked_sub(start);
+
+			match op {
+				Op::Jump(addr) => {
+					queue.extend(target(addr).map(|i| (i, index)));
+					break;
+				}
+				Op::Call(addr) => {
+					queue.extend(target(addr).map(|i| (i, None)));
+					index = None;
+				}
+				Op::Return | Op::Exit | Op::JumpOffset(..) => break,
+				op if op.is_skip() => {
+					// The skipped instruction may be a 4 bytes long `F000 nnnn`
+					let skipped = if next + 1 < program.len() {
+						Op::skip_len(word(program, next), mode)
+					} else {
+						2
+					};
+					queue.push((next + skipped as usize, index));
+				}
+
+				Op::SetIndex(addr) => {
+					index = target(addr);
+					if let Some(i) = index {
+						mark_data(&mut kinds, i, 1);
+					}
+				}
+				Op::LongIndex => {
+					index = target(word(program, i + 2));
+				}
+				Op::Draw(_, _, n) => {
+					if let Some(i) = index {
+						let len = if n == 0 { 32 } else { n as usize };
+						mark_data(&mut kinds, i, len);
+					}
+				}
+				Op::AddIndex(_) | Op::Font(_) | Op::BigFont(_) => index = None,
+				Op::Store(_) | Op::Read(_) | Op::StoreRange(..) | Op::ReadRange(..) => {
+					// `I` may be changed depending on the quirks
+					index = None;
+				}
+				_ => (),
+			}
+
+			i = next;
+		}
+	}
+
+	kinds
+}
+
+fn mark_data(kinds: &mut [Option<LineKind>], from: usize, len: usize) {
+	for kind in kinds.iter_mut().skip(from).take(len) {
+		if kind.is_none() {
+			*kind = Some(LineKind::Data);
+		}
+	}
+}
+
+fn word(program: &[u8], i: usize) -> u16 {
+	let hi = program.get(i).copied().unwrap_or(0) as u16;
+	let lo = program.get(i + 1).copied().unwrap_or(0) as u16;
+	(hi << 8) | lo
+}
@@ -0,0 +1,85 @@
+use std::{collections::VecDeque, fmt};
+
+/// Error occurred while running the program
+/// `pc` is the address of the instruction that caused it
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EmuError {
+	/// Instruction is unknown in the current mode
+	UnknownOpcode { pc: u16, op: u16 },
+	/// Subroutine call with the full stack
+	StackOverflow { pc: u16 },
+	/// Return with the empty stack
+	StackUnderflow { pc: u16 },
+	/// Memory access starting at `addr` goes past the end of the memory of the current mode
+	MemoryOutOfBounds { pc: u16, addr: u16 },
+}
+impl EmuError {
+	pub fn pc(&self) -> u16 {
+		match *self {
+			Self::UnknownOpcode { pc, .. } => pc,
+			Self::StackOverflow { pc } => pc,
+			Self::StackUnderflow { pc } => pc,
+			Self::MemoryOutOfBounds { pc, .. } => pc,
+		}
+	}
+}
+impl fmt::Display for EmuError {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		match self {
+			Self::UnknownOpcode { pc, op } => write!(f, "Unknown opcode {op:04X} at 0x{pc:04X}"),
+			Self::StackOverflow { pc } => write!(f, "Stack overflow at 0x{pc:04X}"),
+			Self::StackUnderflow { pc } => write!(f, "Stack underflow at 0x{pc:04X}"),
+			Self::MemoryOutOfBounds { pc, addr } => {
+				write!(f, "Memory access to 0x{addr:04X} at 0x{pc:04X}")
+			}
+		}
+	}
+}
+
+/// What happens when the program accesses memory past the end of the memory of the current mode
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum MemoryPolicy {
+	/// Addresses wrap around to the start of the memory
+	Wrap,
+	/// Access is skipped and reported as an error
+	#[default]
+	Fault,
+}
+impl MemoryPolicy {
+	pub fn from_name(name: &str) -> Option<Self> {
+		match name {
+			"wrap" => Some(Self::Wrap),
+			"fault" => Some(Self::Fault),
+			_ => None,
+		}
+	}
+}
+
+/// Few most recent emulator errors
+#[derive(Debug, Default, Clone)]
+pub struct ErrorLog {
+	entries: VecDeque<EmuError>,
+	/// Number of errors ever pushed, tells whether a new error has occurred
+	pub count: u32,
+}
+impl ErrorLog {
+	pub const CAPACITY: usize = 16;
+
+	pub fn push(&mut self, error: EmuError) {
+		if self.entries.len() == Self::CAPACITY {
+			self.entries.pop_front();
+		}
+		self.entries.push_back(error);
+		self.count = self.count.wrapping_add(1);
+	}
+	pub fn last(&self) -> Option<&EmuError> {
+		self.entries.back()
+	}
+	/// Errors from the oldest to the newest
+	pub fn iter(&self) -> impl Iterator<Item = &EmuError> {
+		self.entries.iter()
+	}
+	pub fn is_empty(&self) -> bool {
+		self.entries.is_empty()
+	}
+}
@@ -0,0 +1,57 @@
+use super::*;
+
+const PROGRAMS: u64 = 400;
+const STEPS: u32 = 5_000;
+
+/// Random emulator configuration with a random program loaded
+fn random_emu(rng: &mut Rng) -> Emu {
+	let mut emu = Emu {
+		mode: [Mode::Chip8, Mode::SuperChip, Mode::XoChip][rng.next_u64() as usize % 3],
+		quirks: [
+			QuirksPreset::Legacy,
+			QuirksPreset::CosmacVip,
+			QuirksPreset::Chip48,
+			QuirksPreset::SuperChip,
+			QuirksPreset::Modern,
+		][rng.next_u64() as usize % 5]
+			.quirks(),
+		memory_policy: if rng.next_u8() < 128 {
+			MemoryPolicy::Wrap
+		} else {
+			MemoryPolicy::Fault
+		},
+		strict: rng.next_u8() < 64,
+		..Default::default()
+	};
+	emu.set_seed(rng.next_u64());
+
+	let len = rng.next_u64() as usize % Emu::max_program_size(emu.mode);
+	let program: Vec<u8> = (0..len).map(|_| rng.next_u8()).collect();
+	emu.load(&program);
+	emu
+}
+
+/// Random programs must never panic the emulator, whatever they do
+#[test]
+fn random_programs() {
+	let mut rng = Rng::new(0xC8);
+
+	for _ in 0..PROGRAMS {
+		let mut emu = random_emu(&mut rng);
+
+		for step in 0..STEPS {
+			// Restart programs which have exited or halted on an error
+			if emu.exited || emu.debugger.paused {
+				emu.setup();
+			}
+
+			if step % 100 == 0 {
+				let key = rng.next_u8() % Emu::KEYS_COUNT as u8;
+				emu.set_pressed_key(key, rng.next_u8() < 128, true);
+				emu.update_timers();
+			}
+
+			emu.step();
+		}
+	}
+}
@@ -0,0 +1,39 @@
+/// CHIP-8 extension the emulator runs
+/// Each mode is a superset of the previous one
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Mode {
+	/// Original CHIP-8 instruction set only
+	#[default]
+	Chip8,
+	/// SUPER-CHIP 1.1: hi-res mode, scrolling, 16x16 sprites, big font and RPL flags
+	SuperChip,
+	/// XO-CHIP: 64 KiB memory, bitplanes and audio patterns
+	XoChip,
+}
+impl Mode {
+	pub fn from_name(name: &str) -> Option<Self> {
+		match name {
+			"chip8" | "chip-8" => Some(Self::Chip8),
+			"schip" | "superchip" | "super-chip" => Some(Self::SuperChip),
+			"xochip" | "xo-chip" => Some(Self::XoChip),
+			_ => None,
+		}
+	}
+	/// Mode with the `mode as u8` index
+	pub fn from_index(index: u8) -> Option<Self> {
+		match index {
+			0 => Some(Self::Chip8),
+			1 => Some(Self::SuperChip),
+			2 => Some(Self::XoChip),
+			_ => None,
+		}
+	}
+	/// Mode implied by the ROM file extension
+	pub fn from_extension(ext: &str) -> Option<Self> {
+		match ext {
+			"sc8" => Some(Self::SuperChip),
+			"xo8" => Some(Self::XoChip),
+			_ => None,
+		}
+	}
+}
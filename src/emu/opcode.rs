@@ -0,0 +1,366 @@
+use std::fmt;
+
+use super::Mode;
+
+/// Decoded instruction
+/// `x` and `y` are register indices, `n` is a nibble, `kk` is a byte and `nnn` is an address
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Op {
+	/// `00E0`
+	Clear,
+	/// `00EE`
+	Return,
+	/// `00Cn`
+	ScrollDown(u8),
+	/// `00Dn`
+	ScrollUp(u8),
+	/// `00FB`
+	ScrollRight,
+	/// `00FC`
+	ScrollLeft,
+	/// `00FD`
+	Exit,
+	/// `00FE`
+	Lores,
+	/// `00FF`
+	Hires,
+	/// `0nnn` machine code routine, ignored
+	Sys(u16),
+
+	/// `1nnn`
+	Jump(u16),
+	/// `Bnnn`, the register is used only with the jump quirk
+	JumpOffset(u8, u16),
+	/// `2nnn`
+	Call(u16),
+
+	/// `3xkk`
+	SkipEqByte(u8, u8),
+	/// `4xkk`
+	SkipNeByte(u8, u8),
+	/// `5xy0`
+	SkipEq(u8, u8),
+	/// `9xy0`
+	SkipNe(u8, u8),
+	/// `Ex9E`
+	SkipKey(u8),
+	/// `ExA1`
+	SkipNotKey(u8),
+	/// `Fx0A`
+	WaitKey(u8),
+
+	/// `5xy2`
+	StoreRange(u8, u8),
+	/// `5xy3`
+	ReadRange(u8, u8),
+
+	/// `6xkk`
+	SetByte(u8, u8),
+	/// `7xkk`
+	AddByte(u8, u8),
+	/// `8xy0`
+	Set(u8, u8),
+	/// `8xy1`
+	Or(u8, u8),
+	/// `8xy2`
+	And(u8, u8),
+	/// `8xy3`
+	Xor(u8, u8),
+	/// `8xy4`
+	Add(u8, u8),
+	/// `8xy5`
+	Sub(u8, u8),
+	/// `8xy6`
+	ShiftRight(u8, u8),
+	/// `8xy7`
+	SubN(u8, u8),
+	/// `8xyE`
+	ShiftLeft(u8, u8),
+	/// `Cxkk`
+	Rand(u8, u8),
+
+	/// `Fx07`
+	GetDelay(u8),
+	/// `Fx15`
+	SetDelay(u8),
+	/// `Fx18`
+	SetSound(u8),
+
+	/// `Annn`
+	SetIndex(u16),
+	/// `F000 nnnn`, the address is stored in the next word
+	LongIndex,
+	/// `Fx1E`
+	AddIndex(u8),
+	/// `Fx29`
+	Font(u8),
+	/// `Fx30`
+	BigFont(u8),
+	/// `Fx33`
+	Bcd(u8),
+	/// `Fx55`
+	Store(u8),
+	/// `Fx65`
+	Read(u8),
+	/// `Fx75`
+	StoreRpl(u8),
+	/// `Fx85`
+	ReadRpl(u8),
+
+	/// `Dxyn`
+	Draw(u8, u8, u8),
+	/// `Fn01`
+	Planes(u8),
+	/// `F002`
+	Audio,
+	/// `Fx3A`
+	Pitch(u8),
+
+	/// Instruction unknown in the mode
+	Unknown(u16),
+}
+impl Op {
+	/// Decode the instruction with the instruction set of the mode
+	pub fn decode(ins: u16, mode: Mode) -> Self {
+		// Split instruction into 4 nibbles
+		let a = ((ins & 0xF000) >> 12) as u8;
+		let b = ((ins & 0x0F00) >> 8) as u8;
+		let c = ((ins & 0x00F0) >> 4) as u8;
+		let d = (ins & 0x000F) as u8;
+
+		let x = b;
+		let y = c;
+		let n = d; // nibble
+		let addr = ins & 0x0FFF; // nnn
+		let byte = (ins & 0x00FF) as u8; // kk
+
+		let schip = mode >= Mode::SuperChip;
+		let xo = mode >= Mode::XoChip;
+
+		match (a, b, c, d) {
+			(0, 0, 0xe, 0) => Self::Clear,
+			(0, 0, 0xe, 0xe) => Self::Return,
+			(0, 0, 0xc, _) if schip => Self::ScrollDown(n),
+			(0, 0, 0xd, _) if xo => Self::ScrollUp(n),
+			(0, 0, 0xf, 0xb) if schip => Self::ScrollRight,
+			(0, 0, 0xf, 0xc) if schip => Self::ScrollLeft,
+			(0, 0, 0xf, 0xd) if schip => Self::Exit,
+			(0, 0, 0xf, 0xe) if schip => Self::Lores,
+			(0, 0, 0xf, 0xf) if schip => Self::Hires,
+			(0, _, _, _) => Self::Sys(addr),
+
+			(1, _, _, _) => Self::Jump(addr),
+			(0xb, _, _, _) => Self::JumpOffset(x, addr),
+			(2, _, _, _) => Self::Call(addr),
+
+			(3, _, _, _) => Self::SkipEqByte(x, byte),
+			(4, _, _, _) => Self::SkipNeByte(x, byte),
+			(5, _, _, 0) => Self::SkipEq(x, y),
+			(9, _, _, 0) => Self::SkipNe(x, y),
+			(0xe, _, 9, 0xe) => Self::SkipKey(x),
+			(0xe, _, 0xa, 1) => Self::SkipNotKey(x),
+			(0xf, _, 0, 0xa) => Self::WaitKey(x),
+
+			(5, _, _, 2) if xo => Self::StoreRange(x, y),
+			(5, _, _, 3) if xo => Self::ReadRange(x, y),
+
+			(6, _, _, _) => Self::SetByte(x, byte),
+			(7, _, _, _) => Self::AddByte(x, byte),
+			(8, _, _, 0) => Self::Set(x, y),
+			(8, _, _, 1) => Self::Or(x, y),
+			(8, _, _, 2) => Self::And(x, y),
+			(8, _, _, 3) => Self::Xor(x, y),
+			(8, _, _, 4) => Self::Add(x, y),
+			(8, _, _, 5) => Self::Sub(x, y),
+			(8, _, _, 6) => Self::ShiftRight(x, y),
+			(8, _, _, 7) => Self::SubN(x, y),
+			(8, _, _, 0xe) => Self::ShiftLeft(x, y),
+			(0xc, _, _, _) => Self::Rand(x, byte),
+
+			(0xf, _, 0, 7) => Self::GetDelay(x),
+			(0xf, _, 1, 5) => Self::SetDelay(x),
+			(0xf, _, 1, 8) => Self::SetSound(x),
+
+			(0xa, _, _, _) => Self::SetIndex(addr),
+			(0xf, 0, 0, 0) if xo => Self::LongIndex,
+			(0xf, _, 1, 0xe) => Self::AddIndex(x),
+			(0xf, _, 2, 9) => Self::Font(x),
+			(0xf, _, 3, 0) if schip => Self::BigFont(x),
+			(0xf, _, 3, 3) => Self::Bcd(x),
+			(0xf, _, 5, 5) => Self::Store(x),
+			(0xf, _, 6, 5) => Self::Read(x),
+			(0xf, _, 7, 5) if schip => Self::StoreRpl(x),
+			(0xf, _, 8, 5) if schip => Self::ReadRpl(x),
+
+			(0xd, _, _, _) => Self::Draw(x, y, n),
+			(0xf, _, 0, 1) if xo => Self::Planes(x),
+			(0xf, 0, 0, 2) if xo => Self::Audio,
+			(0xf, _, 3, 0xa) if xo => Self::Pitch(x),
+
+			_ => Self::Unknown(ins),
+		}
+	}
+
+	/// Encode the instruction, `LongIndex` address has to be written separately
+	pub fn encode(self) -> u16 {
+		let xy = |op: u16, x: u8, y: u8, n: u16| op | (x as u16) << 8 | (y as u16) << 4 | n;
+		let xkk = |op: u16, x: u8, kk: u8| op | (x as u16) << 8 | kk as u16;
+
+		match self {
+			Self::Clear => 0x00E0,
+			Self::Return => 0x00EE,
+			Self::ScrollDown(n) => 0x00C0 | n as u16,
+			Self::ScrollUp(n) => 0x00D0 | n as u16,
+			Self::ScrollRight => 0x00FB,
+			Self::ScrollLeft => 0x00FC,
+			Self::Exit => 0x00FD,
+			Self::Lores => 0x00FE,
+			Self::Hires => 0x00FF,
+			Self::Sys(nnn) => nnn & 0x0FFF,
+
+			Self::Jump(nnn) => 0x1000 | nnn & 0x0FFF,
+			Self::JumpOffset(x, nnn) => 0xB000 | (x as u16) << 8 | nnn & 0x0FFF,
+			Self::Call(nnn) => 0x2000 | nnn & 0x0FFF,
+
+			Self::SkipEqByte(x, kk) => xkk(0x3000, x, kk),
+			Self::SkipNeByte(x, kk) => xkk(0x4000, x, kk),
+			Self::SkipEq(x, y) => xy(0x5000, x, y, 0),
+			Self::SkipNe(x, y) => xy(0x9000, x, y, 0),
+			Self::SkipKey(x) => xkk(0xE000, x, 0x9E),
+			Self::SkipNotKey(x) => xkk(0xE000, x, 0xA1),
+			Self::WaitKey(x) => xkk(0xF000, x, 0x0A),
+
+			Self::StoreRange(x, y) => xy(0x5000, x, y, 2),
+			Self::ReadRange(x, y) => xy(0x5000, x, y, 3),
+
+			Self::SetByte(x, kk) => xkk(0x6000, x, kk),
+			Self::AddByte(x, kk) => xkk(0x7000, x, kk),
+			Self::Set(x, y) => xy(0x8000, x, y, 0),
+			Self::Or(x, y) => xy(0x8000, x, y, 1),
+			Self::And(x, y) => xy(0x8000, x, y, 2),
+			Self::Xor(x, y) => xy(0x8000, x, y, 3),
+			Self::Add(x, y) => xy(0x8000, x, y, 4),
+			Self::Sub(x, y) => xy(0x8000, x, y, 5),
+			Self::ShiftRight(x, y) => xy(0x8000, x, y, 6),
+			Self::SubN(x, y) => xy(0x8000, x, y, 7),
+			Self::ShiftLeft(x, y) => xy(0x8000, x, y, 0xE),
+			Self::Rand(x, kk) => xkk(0xC000, x, kk),
+
+			Self::GetDelay(x) => xkk(0xF000, x, 0x07),
+			Self::SetDelay(x) => xkk(0xF000, x, 0x15),
+			Self::SetSound(x) => xkk(0xF000, x, 0x18),
+
+			Self::SetIndex(nnn) => 0xA000 | nnn & 0x0FFF,
+			Self::LongIndex => 0xF000,
+			Self::AddIndex(x) => xkk(0xF000, x, 0x1E),
+			Self::Font(x) => xkk(0xF000, x, 0x29),
+			Self::BigFont(x) => xkk(0xF000, x, 0x30),
+			Self::Bcd(x) => xkk(0xF000, x, 0x33),
+			Self::Store(x) => xkk(0xF000, x, 0x55),
+			Self::Read(x) => xkk(0xF000, x, 0x65),
+			Self::StoreRpl(x) => xkk(0xF000, x, 0x75),
+			Self::ReadRpl(x) => xkk(0xF000, x, 0x85),
+
+			Self::Draw(x, y, n) => xy(0xD000, x, y, n as u16 & 0xF),
+			Self::Planes(n) => xkk(0xF000, n, 0x01),
+			Self::Audio => 0xF002,
+			Self::Pitch(x) => xkk(0xF000, x, 0x3A),
+
+			Self::Unknown(ins) => ins,
+		}
+	}
+
+	/// Instruction size in bytes
+	pub fn size(self) -> u16 {
+		match self {
+			Self::LongIndex => 4,
+			_ => 2,
+		}
+	}
+	/// Number of bytes skipped by a skip instruction followed by the `next` one
+	/// Only XO-CHIP has the 4 bytes long `F000 nnnn`
+	pub fn skip_len(next: u16, mode: Mode) -> u16 {
+		Self::decode(next, mode).size()
+	}
+	/// Whether the instruction conditionally skips the next one
+	pub fn is_skip(self) -> bool {
+		matches!(
+			self,
+			Self::SkipEqByte(..)
+				| Self::SkipNeByte(..)
+				| Self::SkipEq(..)
+				| Self::SkipNe(..)
+				| Self::SkipKey(_)
+				| Self::SkipNotKey(_)
+		)
+	}
+}
+
+/// Octo-style mnemonic
+/// Skips are written as Octo `if ... then` conditions, so the condition is inverted
+impl fmt::Display for Op {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		match *self {
+			Self::Clear => write!(f, "clear"),
+			Self::Return => write!(f, "return"),
+			Self::ScrollDown(n) => write!(f, "scroll-down {n}"),
+			Self::ScrollUp(n) => write!(f, "scroll-up {n}"),
+			Self::ScrollRight => write!(f, "scroll-right"),
+			Self::ScrollLeft => write!(f, "scroll-left"),
+			Self::Exit => write!(f, "exit"),
+			Self::Lores => write!(f, "lores"),
+			Self::Hires => write!(f, "hires"),
+			Self::Sys(nnn) => write!(f, "0x{:02X} 0x{:02X}", nnn >> 8, nnn & 0xFF),
+
+			Self::Jump(nnn) => write!(f, "jump 0x{nnn:03X}"),
+			Self::JumpOffset(_, nnn) => write!(f, "jump0 0x{nnn:03X}"),
+			Self::Call(nnn) => write!(f, ":call 0x{nnn:03X}"),
+
+			Self::SkipEqByte(x, kk) => write!(f, "if v{x:x} != 0x{kk:02X} then"),
+			Self::SkipNeByte(x, kk) => write!(f, "if v{x:x} == 0x{kk:02X} then"),
+			Self::SkipEq(x, y) => write!(f, "if v{x:x} != v{y:x} then"),
+			Self::SkipNe(x, y) => write!(f, "if v{x:x} == v{y:x} then"),
+			Self::SkipKey(x) => write!(f, "if v{x:x} -key then"),
+			Self::SkipNotKey(x) => write!(f, "if v{x:x} key then"),
+			Self::WaitKey(x) => write!(f, "v{x:x} := key"),
+
+			Self::StoreRange(x, y) => write!(f, "save v{x:x} - v{y:x}"),
+			Self::ReadRange(x, y) => write!(f, "load v{x:x} - v{y:x}"),
+
+			Self::SetByte(x, kk) => write!(f, "v{x:x} := 0x{kk:02X}"),
+			Self::AddByte(x, kk) => write!(f, "v{x:x} += 0x{kk:02X}"),
+			Self::Set(x, y) => write!(f, "v{x:x} := v{y:x}"),
+			Self::Or(x, y) => write!(f, "v{x:x} |= v{y:x}"),
+			Self::And(x, y) => write!(f, "v{x:x} &= v{y:x}"),
+			Self::Xor(x, y) => write!(f, "v{x:x} ^= v{y:x}"),
+			Self::Add(x, y) => write!(f, "v{x:x} += v{y:x}"),
+			Self::Sub(x, y) => write!(f, "v{x:x} -= v{y:x}"),
+			Self::ShiftRight(x, y) => write!(f, "v{x:x} >>= v{y:x}"),
+			Self::SubN(x, y) => write!(f, "v{x:x} =- v{y:x}"),
+			Self::ShiftLeft(x, y) => write!(f, "v{x:x} <<= v{y:x}"),
+			Self::Rand(x, kk) => write!(f, "v{x:x} := random 0x{kk:02X}"),
+
+			Self::GetDelay(x) => write!(f, "v{x:x} := delay"),
+			Self::SetDelay(x) => write!(f, "delay := v{x:x}"),
+			Self::SetSound(x) => write!(f, "buzzer := v{x:x}"),
+
+			Self::SetIndex(nnn) => write!(f, "i := 0x{nnn:03X}"),
+			Self::LongIndex => write!(f, "i := long"),
+			Self::AddIndex(x) => write!(f, "i += v{x:x}"),
+			Self::Font(x) => write!(f, "i := hex v{x:x}"),
+			Self::BigFont(x) => write!(f, "i := bighex v{x:x}"),
+			Self::Bcd(x) => write!(f, "bcd v{x:x}"),
+			Self::Store(x) => write!(f, "save v{x:x}"),
+			Self::Read(x) => write!(f, "load v{x:x}"),
+			Self::StoreRpl(x) => write!(f, "saveflags v{x:x}"),
+			Self::ReadRpl(x) => write!(f, "loadflags v{x:x}"),
+
+			Self::Draw(x, y, n) => write!(f, "sprite v{x:x} v{y:x} {n}"),
+			Self::Planes(n) => write!(f, "plane {n}"),
+			Self::Audio => write!(f, "audio"),
+			Self::Pitch(x) => write!(f, "pitch := v{x:x}"),
+
+			Self::Unknown(ins) => write!(f, "0x{:02X} 0x{:02X}", ins >> 8, ins & 0xFF),
+		}
+	}
+}
@@ -0,0 +1,98 @@
+/// How `Fx55`/`Fx65` affect `I`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LoadStore {
+	/// `I = I + x + 1`
+	Increment,
+	/// `I = I + x`
+	IncrementX,
+	/// `I` is left untouched
+	Keep,
+}
+
+/// Behaviours of ambiguous CHIP-8 instructions that differ between interpreters
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Quirks {
+	/// `8xy6`/`8xyE` shift `Vy` and store the result in `Vx`, otherwise `Vx` is shifted in place
+	pub shift_vy: bool,
+	/// How `Fx55`/`Fx65` affect `I`
+	pub load_store: LoadStore,
+	/// `8xy1`/`8xy2`/`8xy3` reset `VF` to zero
+	pub vf_reset: bool,
+	/// `Bnnn` jumps to `nnn + Vx` instead of `nnn + V0`
+	pub jump_vx: bool,
+	/// `Dxyn` clips sprites at the screen edges instead of wrapping them around
+	pub clip_sprites: bool,
+}
+impl Default for Quirks {
+	fn default() -> Self {
+		QuirksPreset::default().quirks()
+	}
+}
+
+/// Named quirks profile
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum QuirksPreset {
+	/// Behaviour of the emulator before the quirks were configurable
+	#[default]
+	Legacy,
+	/// Original interpreter of the COSMAC VIP
+	CosmacVip,
+	/// CHIP-48 interpreter of the HP-48 calculators
+	Chip48,
+	/// SUPER-CHIP 1.1
+	SuperChip,
+	/// Modern interpreters like Octo
+	Modern,
+}
+impl QuirksPreset {
+	pub fn quirks(self) -> Quirks {
+		match self {
+			Self::Legacy => Quirks {
+				shift_vy: false,
+				load_store: LoadStore::Keep,
+				vf_reset: false,
+				jump_vx: false,
+				clip_sprites: false,
+			},
+			Self::CosmacVip => Quirks {
+				shift_vy: true,
+				load_store: LoadStore::Increment,
+				vf_reset: true,
+				jump_vx: false,
+				clip_sprites: true,
+			},
+			Self::Chip48 => Quirks {
+				shift_vy: false,
+				load_store: LoadStore::IncrementX,
+				vf_reset: false,
+				jump_vx: true,
+				clip_sprites: true,
+			},
+			Self::SuperChip => Quirks {
+				shift_vy: false,
+				load_store: LoadStore::Keep,
+				vf_reset: false,
+				jump_vx: true,
+				clip_sprites: true,
+			},
+			Self::Modern => Quirks {
+				shift_vy: true,
+				load_store: LoadStore::Increment,
+				vf_reset: false,
+				jump_vx: false,
+				clip_sprites: false,
+			},
+		}
+	}
+
+	pub fn from_name(name: &str) -> Option<Self> {
+		match name {
+			"legacy" => Some(Self::Legacy),
+			"vip" | "cosmac-vip" => Some(Self::CosmacVip),
+			"chip48" | "chip-48" => Some(Self::Chip48),
+			"schip" | "superchip" | "super-chip" => Some(Self::SuperChip),
+			"modern" | "octo" => Some(Self::Modern),
+			_ => None,
+		}
+	}
+}
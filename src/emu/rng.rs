@@ -0,0 +1,23 @@
+/// Small deterministic PRNG (SplitMix64), so runs with the same seed are reproducible
+#[derive(Debug, Default, Clone)]
+pub struct Rng {
+	pub state: u64,
+}
+impl Rng {
+	pub fn new(seed: u64) -> Self {
+		Self { state: seed }
+	}
+
+	pub fn next_u64(&mut self) -> u64 {
+		self.state = self.state.wrapping_add(0x9E37_79B9_7F4A_7C15);
+
+		let mut z = self.state;
+		z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+		z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+		z ^ (z >> 31)
+	}
+	/// Random byte in the full `0..=255` range
+	pub fn next_u8(&mut self) -> u8 {
+		(self.next_u64() >> 56) as u8
+	}
+}
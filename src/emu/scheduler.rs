@@ -0,0 +1,31 @@
+use std::time::Duration;
+
+/// Fixed timestep scheduler
+/// Converts elapsed real time into a number of 60 Hz ticks
+#[derive(Debug, Default, Clone)]
+pub struct Scheduler {
+	/// Elapsed time that is not consumed by ticks yet
+	acc: Duration,
+}
+impl Scheduler {
+	/// Number of ticks per second
+	pub const TICK_RATE: u32 = 60;
+	pub const TICK: Duration = Duration::from_nanos(1_000_000_000 / Self::TICK_RATE as u64);
+	/// Max number of ticks to catch up in a single update
+	/// Anything above is dropped, so a long stall doesn't fast-forward the program
+	pub const MAX_TICKS: u32 = 4;
+
+	/// Accumulate elapsed time and return the number of ticks to run
+	pub fn advance(&mut self, delta: Duration) -> u32 {
+		self.acc += delta;
+
+		let ticks = (self.acc.as_nanos() / Self::TICK.as_nanos()) as u32;
+		if ticks > Self::MAX_TICKS {
+			self.acc = Duration::ZERO;
+			return Self::MAX_TICKS;
+		}
+
+		self.acc -= Self::TICK * ticks;
+		ticks
+	}
+}
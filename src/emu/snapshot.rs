@@ -0,0 +1,156 @@
+use super::{Emu, Registers};
+
+/// Little-endian binary writer of snapshots
+#[derive(Debug, Default)]
+pub struct SnapshotWriter {
+	pub bytes: Vec<u8>,
+}
+impl SnapshotWriter {
+	pub fn u8(&mut self, value: u8) {
+		self.bytes.push(value);
+	}
+	pub fn bool(&mut self, value: bool) {
+		self.u8(value as u8);
+	}
+	pub fn u16(&mut self, value: u16) {
+		self.bytes.extend_from_slice(&value.to_le_bytes());
+	}
+	pub fn u32(&mut self, value: u32) {
+		self.bytes.extend_from_slice(&value.to_le_bytes());
+	}
+	pub fn u64(&mut self, value: u64) {
+		self.bytes.extend_from_slice(&value.to_le_bytes());
+	}
+	pub fn f32(&mut self, value: f32) {
+		self.bytes.extend_from_slice(&value.to_le_bytes());
+	}
+	pub fn slice(&mut self, slice: &[u8]) {
+		self.bytes.extend_from_slice(slice);
+	}
+}
+
+/// Little-endian binary reader of snapshots
+/// Every method returns `None` if the data is truncated
+#[derive(Debug)]
+pub struct SnapshotReader<'a> {
+	bytes: &'a [u8],
+	pos: usize,
+}
+impl<'a> SnapshotReader<'a> {
+	pub fn new(bytes: &'a [u8]) -> Self {
+		Self { bytes, pos: 0 }
+	}
+
+	pub fn slice(&mut self, len: usize) -> Option<&'a [u8]> {
+		let slice = self.bytes.get(self.pos..self.pos.checked_add(len)?)?;
+		self.pos += len;
+		Some(slice)
+	}
+	pub fn array<const N: usize>(&mut self) -> Option<[u8; N]> {
+		self.slice(N)?.try_into().ok()
+	}
+	/// Rest of the data
+	pub fn rest(&mut self) -> &'a [u8] {
+		let rest = &self.bytes[self.pos..];
+		self.pos = self.bytes.len();
+		rest
+	}
+
+	pub fn u8(&mut self) -> Option<u8> {
+		Some(self.array::<1>()?[0])
+	}
+	pub fn bool(&mut self) -> Option<bool> {
+		Some(self.u8()? > 0)
+	}
+	pub fn u16(&mut self) -> Option<u16> {
+		Some(u16::from_le_bytes(self.array()?))
+	}
+	pub fn u32(&mut self) -> Option<u32> {
+		Some(u32::from_le_bytes(self.array()?))
+	}
+	pub fn u64(&mut self) -> Option<u64> {
+		Some(u64::from_le_bytes(self.array()?))
+	}
+	pub fn f32(&mut self) -> Option<f32> {
+		Some(f32::from_le_bytes(self.array()?))
+	}
+}
+
+impl Emu {
+	/// Write the machine state, configuration and the program itself are not included
+	pub fn write_snapshot(&self, w: &mut SnapshotWriter) {
+		w.u16(self.pc);
+		w.u16(self.sp);
+		w.u16(self.index);
+		for addr in self.stack {
+			w.u16(addr);
+		}
+
+		w.slice(&self.memory);
+		w.slice(&self.regs);
+		w.u8(self.delay_timer);
+		w.u8(self.sound_timer);
+		w.slice(&self.rpl);
+
+		w.slice(&self.screen);
+		w.bool(self.hires);
+		w.u8(self.planes);
+
+		w.bool(self.audio_pattern.is_some());
+		w.slice(&self.audio_pattern.unwrap_or_default());
+		w.u8(self.pitch);
+
+		w.u8(self.cur_ins.0);
+		w.u8(self.cur_ins.1);
+		w.u8(self.wait_for_keypress.unwrap_or(u8::MAX));
+		w.bool(self.exited);
+
+		w.f32(self.speed);
+		w.f32(self.heat);
+		w.f32(self.ins_budget);
+		w.u32(self.frame);
+		w.u64(self.seed);
+		w.u64(self.rng.state);
+	}
+	/// Read the machine state written by [`Emu::write_snapshot`]
+	/// Returns `None` and leaves the emulator partially updated if the data is truncated
+	pub fn read_snapshot(&mut self, r: &mut SnapshotReader) -> Option<()> {
+		self.pc = r.u16()?;
+		self.sp = r.u16()?.min(Self::STACK_SIZE as u16);
+		self.index = r.u16()?;
+		for addr in self.stack.iter_mut() {
+			*addr = r.u16()?;
+		}
+
+		self.memory = r.array()?;
+		self.regs.copy_from_slice(r.slice(Registers::COUNT)?);
+		self.delay_timer = r.u8()?;
+		self.sound_timer = r.u8()?;
+		self.rpl = r.array()?;
+
+		self.screen = r.array()?;
+		self.hires = r.bool()?;
+		self.planes = r.u8()? & 0b11;
+
+		let has_pattern = r.bool()?;
+		let pattern = r.array()?;
+		self.audio_pattern = has_pattern.then_some(pattern);
+		self.pitch = r.u8()?;
+
+		self.cur_ins = (r.u8()?, r.u8()?);
+		self.wait_for_keypress = match r.u8()? {
+			u8::MAX => None,
+			x => Some(x & 0xF),
+		};
+		self.exited = r.bool()?;
+
+		self.set_speed(r.f32()?);
+		self.heat = r.f32()?.clamp(0.0, 1.0);
+		self.ins_budget = r.f32()?;
+		self.frame = r.u32()?;
+		self.seed = r.u64()?;
+		self.rng.state = r.u64()?;
+
+		Some(())
+	}
+}
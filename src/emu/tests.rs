@@ -0,0 +1,509 @@
+use std::{fs, path::Path};
+
+use super::*;
+use crate::rom::read_rom;
+
+/// Emulator with the instructions loaded at the program start
+/// Boxed because tests create a lot of them and the emulator is big
+fn new_emu(mode: Mode, preset: QuirksPreset, ins: &[u16]) -> Box<Emu> {
+	let program: Vec<u8> = ins.iter().flat_map(|ins| ins.to_be_bytes()).collect();
+
+	let mut emu = Box::new(Emu {
+		mode,
+		quirks: preset.quirks(),
+		..Default::default()
+	});
+	emu.set_seed(1);
+	emu.load(&program);
+	emu
+}
+/// Execute every instruction once in the SUPER-CHIP mode with the modern quirks
+fn run(ins: &[u16]) -> Box<Emu> {
+	run_with(Mode::SuperChip, QuirksPreset::Modern, ins)
+}
+fn run_with(mode: Mode, preset: QuirksPreset, ins: &[u16]) -> Box<Emu> {
+	let mut emu = new_emu(mode, preset, ins);
+	steps(&mut emu, ins.len());
+	emu
+}
+fn steps(emu: &mut Emu, n: usize) {
+	for _ in 0..n {
+		emu.step();
+	}
+}
+/// Lit pixels of the screen row
+fn row(emu: &Emu, y: usize) -> String {
+	(0..emu.screen_width())
+		.map(|x| if emu.pixel(x, y) > 0 { '#' } else { '.' })
+		.collect()
+}
+
+#[test]
+fn registers() {
+	// v1 := 0x12; v2 := v1; v2 += 0xF0; vf := 5; v3 := 0xFF; v3 += 2
+	let emu = run(&[0x6112, 0x8210, 0x72F0, 0x6F05, 0x63FF, 0x7302]);
+	assert_eq!(emu.regs[1], 0x12);
+	assert_eq!(emu.regs[2], 0x02);
+	assert_eq!(emu.regs[3], 0x01);
+	// 7xkk never touches the flag
+	assert_eq!(emu.regs[0xF], 5);
+}
+
+#[test]
+fn logic() {
+	// v1 := 0b1100; v2 := 0b1010; vf := 7; v1 |= v2; v3 := 0b1100; v3 &= v2; v4 := 0b1100; v4 ^= v2
+	let ins = [
+		0x610C, 0x620A, 0x6F07, 0x8121, 0x630C, 0x8322, 0x640C, 0x8423,
+	];
+	let emu = run(&ins);
+	assert_eq!(emu.regs[1], 0b1110);
+	assert_eq!(emu.regs[3], 0b1000);
+	assert_eq!(emu.regs[4], 0b0110);
+	assert_eq!(emu.regs[0xF], 7);
+
+	let emu = run_with(Mode::Chip8, QuirksPreset::CosmacVip, &ins[..4]);
+	assert_eq!(emu.regs[0xF], 0);
+}
+
+#[test]
+fn arithmetic_flags() {
+	// Vx += Vy
+	let emu = run(&[0x610A, 0x6214, 0x8124]);
+	assert_eq!((emu.regs[1], emu.regs[0xF]), (30, 0));
+	let emu = run(&[0x61C8, 0x6264, 0x8124]);
+	assert_eq!((emu.regs[1], emu.regs[0xF]), (44, 1));
+
+	// Vx -= Vy, VF is set when there is no borrow
+	let emu = run(&[0x611E, 0x620A, 0x8125]);
+	assert_eq!((emu.regs[1], emu.regs[0xF]), (20, 1));
+	let emu = run(&[0x610A, 0x620A, 0x8125]);
+	assert_eq!((emu.regs[1], emu.regs[0xF]), (0, 1));
+	let emu = run(&[0x610A, 0x621E, 0x8125]);
+	assert_eq!((emu.regs[1], emu.regs[0xF]), (236, 0));
+
+	// Vx = Vy - Vx
+	let emu = run(&[0x610A, 0x621E, 0x8127]);
+	assert_eq!((emu.regs[1], emu.regs[2], emu.regs[0xF]), (20, 30, 1));
+	let emu = run(&[0x610A, 0x620A, 0x8127]);
+	assert_eq!((emu.regs[1], emu.regs[0xF]), (0, 1));
+	let emu = run(&[0x611E, 0x620A, 0x8127]);
+	assert_eq!((emu.regs[1], emu.regs[0xF]), (236, 0));
+
+	// The flag is written after the result when VF is the destination
+	let emu = run(&[0x6FC8, 0x6164, 0x8F14]);
+	assert_eq!(emu.regs[0xF], 1);
+	let emu = run(&[0x6F0A, 0x6114, 0x8F15]);
+	assert_eq!(emu.regs[0xF], 0);
+	let emu = run(&[0x6F1E, 0x610A, 0x8F17]);
+	assert_eq!(emu.regs[0xF], 0);
+}
+
+#[test]
+fn shifts() {
+	// v1 := 5; v2 := 0x81; v1 >>= v2; v3 := 0x41; v3 <<= v2
+	let ins = [0x6105, 0x6281, 0x8126, 0x6341, 0x832E];
+
+	// Vy is shifted into Vx
+	let emu = run(&ins[..3]);
+	assert_eq!((emu.regs[1], emu.regs[0xF]), (0x40, 1));
+	let emu = run(&ins);
+	assert_eq!((emu.regs[3], emu.regs[0xF]), (0x02, 1));
+
+	// Vx is shifted in place
+	let emu = run_with(Mode::SuperChip, QuirksPreset::SuperChip, &ins[..3]);
+	assert_eq!((emu.regs[1], emu.regs[0xF]), (0x02, 1));
+	let emu = run_with(Mode::SuperChip, QuirksPreset::SuperChip, &ins);
+	assert_eq!((emu.regs[3], emu.regs[0xF]), (0x82, 0));
+
+	// vf := 0x40; vf <<= vf
+	let emu = run(&[0x6F40, 0x8FFE]);
+	assert_eq!(emu.regs[0xF], 0);
+}
+
+#[test]
+fn legacy_quirks() {
+	// ROMs without a known profile run as before the quirks were configurable
+	assert_eq!(QuirksPreset::default(), QuirksPreset::Legacy);
+
+	// v1 := 5; v2 := 0x81; v1 >>= v2; i := 0x300; save v1; v0 := 2; jump0 0x140
+	let ins = [0x6105, 0x6281, 0x8126, 0xA300, 0xF155, 0x6002, 0xB140];
+	let emu = run_with(Mode::Chip8, QuirksPreset::Legacy, &ins[..5]);
+	assert_eq!((emu.regs[1], emu.regs[0xF]), (0x02, 1));
+	assert_eq!(emu.index, 0x300);
+	let emu = run_with(Mode::Chip8, QuirksPreset::Legacy, &ins);
+	assert_eq!(emu.pc, 0x142);
+}
+
+#[test]
+fn random() {
+	// v1 := random 0x0F
+	let a = run(&[0xC10F, 0xC10F, 0xC10F]);
+	let b = run(&[0xC10F, 0xC10F, 0xC10F]);
+	assert_eq!(a.regs[1], b.regs[1]);
+	assert_eq!(a.regs[1] & 0xF0, 0);
+}
+
+#[test]
+fn jumps_and_calls() {
+	// jump 0x206; <skipped>; <skipped>; v1 := 1
+	let emu = run(&[0x1206, 0x6102, 0x6103, 0x6101]);
+	assert_eq!(emu.regs[1], 1);
+
+	// call 0x206; v1 := 2; exit; v2 := 3; return
+	let mut emu = new_emu(
+		Mode::SuperChip,
+		QuirksPreset::Modern,
+		&[0x2206, 0x6102, 0x00FD, 0x6203, 0x00EE],
+	);
+	steps(&mut emu, 5);
+	assert!(emu.exited);
+	assert_eq!((emu.regs[1], emu.regs[2], emu.sp), (2, 3, 0));
+
+	// v0 := 2; v1 := 4; jump0 0x140
+	let ins = [0x6002, 0x6104, 0xB140];
+	let emu = run(&ins);
+	assert_eq!(emu.pc, 0x142);
+	let emu = run_with(Mode::SuperChip, QuirksPreset::SuperChip, &ins);
+	assert_eq!(emu.pc, 0x144);
+}
+
+#[test]
+fn stack_overflow() {
+	// call 0x200
+	let mut emu = new_emu(Mode::SuperChip, QuirksPreset::Modern, &[0x2200]);
+	emu.strict = true;
+
+	// The whole stack can be used
+	steps(&mut emu, Emu::STACK_SIZE);
+	assert_eq!(emu.sp as usize, Emu::STACK_SIZE);
+	assert!(emu.errors.is_empty());
+
+	steps(&mut emu, 1);
+	assert_eq!(emu.sp as usize, Emu::STACK_SIZE);
+	assert_eq!(
+		emu.debugger.hit,
+		Some(Hit::Error(EmuError::StackOverflow { pc: 0x200 }))
+	);
+}
+
+#[test]
+fn snapshot_seed() {
+	// v1 := random 0xFF
+	let mut emu = new_emu(Mode::Chip8, QuirksPreset::Legacy, &[0xC1FF]);
+	emu.set_seed(42);
+	let mut w = SnapshotWriter::default();
+	emu.write_snapshot(&mut w);
+
+	// The restored machine is reset with the saved seed
+	let mut other = new_emu(Mode::Chip8, QuirksPreset::Legacy, &[0xC1FF]);
+	other
+		.read_snapshot(&mut SnapshotReader::new(&w.bytes))
+		.unwrap();
+	for emu in [&mut emu, &mut other] {
+		emu.reset();
+		emu.step();
+	}
+	assert_eq!(other.seed, 42);
+	assert_eq!(other.regs[1], emu.regs[1]);
+}
+
+#[test]
+fn skips() {
+	let skipped = |ins: &[u16]| {
+		let emu = run(ins);
+		emu.pc == Emu::PROGRAM_START_ADDR as u16 + ins.len() as u16 * 2 + 2
+	};
+
+	// v1 := 5; v2 := 5; v3 := 6
+	let setup = [0x6105, 0x6205, 0x6306];
+	let with = |ins: u16| [setup.as_slice(), &[ins]].concat();
+
+	assert!(skipped(&with(0x3105)));
+	assert!(!skipped(&with(0x3106)));
+	assert!(skipped(&with(0x4106)));
+	assert!(!skipped(&with(0x4105)));
+	assert!(skipped(&with(0x5120)));
+	assert!(!skipped(&with(0x5130)));
+	assert!(skipped(&with(0x9130)));
+	assert!(!skipped(&with(0x9120)));
+}
+
+#[test]
+fn keys() {
+	// v1 := 7; if v1 -key then v2 := 1; if v1 key then v3 := 1
+	let ins = [0x6107, 0xE19E, 0x6201, 0xE1A1, 0x6301];
+	let mut emu = new_emu(Mode::SuperChip, QuirksPreset::Modern, &ins);
+	emu.set_pressed_key(7, true, true);
+	steps(&mut emu, 4);
+	assert_eq!((emu.regs[2], emu.regs[3]), (0, 1));
+	assert!(emu.key_checked);
+
+	// v2 := key; v3 := 1
+	let mut emu = new_emu(Mode::SuperChip, QuirksPreset::Modern, &[0xF20A, 0x6301]);
+	steps(&mut emu, 3);
+	assert_eq!(emu.wait_for_keypress, Some(2));
+	assert_eq!(emu.regs[3], 0);
+
+	emu.set_pressed_key(0xA, true, false);
+	emu.step();
+	assert_eq!(emu.wait_for_keypress, Some(2));
+
+	emu.set_pressed_key(0xB, true, true);
+	emu.step();
+	assert_eq!(emu.wait_for_keypress, None);
+	assert_eq!((emu.regs[2], emu.regs[3]), (0xB, 1));
+}
+
+#[test]
+fn timers() {
+	// v1 := 3; delay := v1; buzzer := v1; v2 := delay
+	let mut emu = run(&[0x6103, 0xF115, 0xF118, 0xF207]);
+	assert_eq!((emu.delay_timer, emu.sound_timer, emu.regs[2]), (3, 3, 3));
+
+	emu.update_timers();
+	assert_eq!((emu.delay_timer, emu.sound_timer), (2, 2));
+	for _ in 0..5 {
+		emu.tick();
+	}
+	assert_eq!((emu.delay_timer, emu.sound_timer), (0, 0));
+}
+
+#[test]
+fn index() {
+	// i := 0x123; v1 := 0x10; i += v1
+	let emu = run(&[0xA123, 0x6110, 0xF11E]);
+	assert_eq!(emu.index, 0x133);
+	assert_eq!(emu.regs[0xF], 0);
+
+	// v1 := 0xA; i := hex v1; i := bighex v1
+	let emu = run(&[0x610A, 0xF129]);
+	assert_eq!(emu.index, 50);
+	let emu = run(&[0x610A, 0xF130]);
+	assert_eq!(emu.index, Emu::BIG_FONT_ADDR + 100);
+}
+
+#[test]
+fn bcd() {
+	// i := 0x300; v1 := 254; bcd v1
+	let emu = run(&[0xA300, 0x61FE, 0xF133]);
+	assert_eq!(&emu.memory[0x300..0x303], &[2, 5, 4]);
+	assert_eq!(emu.index, 0x300);
+}
+
+#[test]
+fn load_store() {
+	// i := 0x300; v0 := 1; v1 := 2; v2 := 3; save v2; v0 := 0; v1 := 0; i := 0x301; load v1
+	let ins = [
+		0xA300, 0x6001, 0x6102, 0x6203, 0xF255, 0x6000, 0x6100, 0xA301, 0xF165,
+	];
+
+	for (preset, index) in [
+		(QuirksPreset::CosmacVip, 0x303),
+		(QuirksPreset::Chip48, 0x302),
+		(QuirksPreset::SuperChip, 0x301),
+	] {
+		let emu = run_with(Mode::SuperChip, preset, &ins);
+		assert_eq!(&emu.memory[0x300..0x303], &[1, 2, 3]);
+		assert_eq!((emu.regs[0], emu.regs[1]), (2, 3));
+		assert_eq!(emu.index, index, "{preset:?}");
+	}
+
+	// v0 := 5; v1 := 6; saveflags v1; v0 := 0; v1 := 0; loadflags v1
+	let emu = run(&[0x6005, 0x6106, 0xF175, 0x6000, 0x6100, 0xF185]);
+	assert_eq!((emu.regs[0], emu.regs[1]), (5, 6));
+	assert_eq!(&emu.rpl[..2], &[5, 6]);
+}
+
+#[test]
+fn draw() {
+	// v0 := 0; i := hex v0; sprite v0 v0 5
+	let ins = [0x6000, 0xF029, 0xD005];
+	let mut emu = run(&ins);
+	assert_eq!(row(&emu, 0), format!("####{}", ".".repeat(60)));
+	assert_eq!(row(&emu, 1), format!("#..#{}", ".".repeat(60)));
+	assert_eq!(emu.regs[0xF], 0);
+
+	// Drawing again erases the sprite and reports the collision
+	emu.pc -= 2;
+	emu.step();
+	assert!(emu.screen.iter().all(|p| *p == 0));
+	assert_eq!(emu.regs[0xF], 1);
+
+	// clear
+	let emu = run(&[ins[0], ins[1], ins[2], 0x00E0]);
+	assert!(emu.screen.iter().all(|p| *p == 0));
+}
+
+#[test]
+fn draw_wrapping() {
+	// v0 := 0; i := hex v0; v1 := 126; v2 := 31; sprite v1 v2 5
+	let ins = [0x6000, 0xF029, 0x617E, 0x621F, 0xD125];
+
+	// The origin wraps to (62, 31) and the rest of the sprite is clipped
+	let emu = run_with(Mode::SuperChip, QuirksPreset::SuperChip, &ins);
+	assert_eq!(row(&emu, 31), format!("{}##", ".".repeat(62)));
+	assert_eq!(row(&emu, 0), ".".repeat(64));
+
+	// The sprite itself wraps around too
+	let emu = run(&ins);
+	assert_eq!(row(&emu, 31), format!("##{}##", ".".repeat(60)));
+	assert_eq!(row(&emu, 0), format!(".#{}#.", ".".repeat(60)));
+}
+
+#[test]
+fn superchip() {
+	// hires; i := 0x300; v0 := 0; sprite v0 v0 0; <scroll>
+	let square = |scroll: u16| {
+		let ins = [0x00FF, 0xA300, 0x6000, 0xD000, scroll];
+		let mut emu = new_emu(Mode::SuperChip, QuirksPreset::Modern, &ins);
+		emu.memory[0x300..0x320].fill(0xFF);
+		steps(&mut emu, ins.len());
+		emu
+	};
+
+	// 16x16 sprite, then scroll-down 0
+	let emu = square(0x00C0);
+	assert!(emu.hires);
+	assert_eq!(&row(&emu, 15)[..20], "################....");
+	assert_eq!(&row(&emu, 16)[..20], "....................");
+
+	// scroll-down 2
+	let emu = square(0x00C2);
+	assert_eq!(&row(&emu, 1)[..20], "....................");
+	assert_eq!(&row(&emu, 17)[..20], "################....");
+	// scroll-right
+	let emu = square(0x00FB);
+	assert_eq!(&row(&emu, 0)[..24], "....################....");
+	// scroll-left
+	let emu = square(0x00FC);
+	assert_eq!(&row(&emu, 0)[..16], "############....");
+
+	// v0 := 0; i := bighex v0; sprite v0 v0 10
+	let emu = run(&[0x6000, 0xF030, 0xD00A]);
+	let line = format!("{:08b}", BIG_FONT[0])
+		.replace('0', ".")
+		.replace('1', "#");
+	assert_eq!(&row(&emu, 0)[..8], line);
+
+	// lores; exit
+	let emu = run(&[0x00FF, 0x00FE, 0x00FD]);
+	assert!(!emu.hires);
+	assert!(emu.exited);
+}
+
+#[test]
+fn xochip() {
+	// i := long 0x1234; v1 := 0x80; pitch := v1; plane 3
+	let emu = run_with(
+		Mode::XoChip,
+		QuirksPreset::Modern,
+		&[0xF000, 0x1234, 0x6180, 0xF13A, 0xF301],
+	);
+	assert_eq!(emu.index, 0x1234);
+	assert_eq!(emu.pitch, 0x80);
+	assert_eq!(emu.planes, 3);
+
+	// v1 := 1; if v1 != 1 then i := long 0x1234; v2 := 2
+	let emu = run_with(
+		Mode::XoChip,
+		QuirksPreset::Modern,
+		&[0x6101, 0x3101, 0xF000, 0x1234, 0x6202],
+	);
+	assert_eq!(emu.index, 0);
+	assert_eq!(emu.regs[2], 2);
+
+	// i := 0x300; v1 := 1; v2 := 2; v3 := 3; save v3 - v1; load v1 - v3
+	let ins = [0xA300, 0x6101, 0x6202, 0x6303, 0x5312, 0x5133];
+	let emu = run_with(Mode::XoChip, QuirksPreset::Modern, &ins);
+	assert_eq!(&emu.memory[0x300..0x303], &[3, 2, 1]);
+	assert_eq!((emu.regs[1], emu.regs[3]), (3, 1));
+	assert_eq!(emu.index, 0x300);
+
+	// i := 0; audio
+	let emu = run_with(Mode::XoChip, QuirksPreset::Modern, &[0xA000, 0xF002]);
+	assert_eq!(emu.audio_pattern.map(|p| p[..5] == FONT[..5]), Some(true));
+
+	// plane 2; i := hex v0; sprite v0 v0 5; plane 3; sprite v0 v0 5
+	let ins = [0xF201, 0xF029, 0xD005, 0xF301, 0xD005];
+	let emu = run_with(Mode::XoChip, QuirksPreset::Modern, &ins[..3]);
+	assert_eq!(emu.pixel(0, 0), 2);
+	assert_eq!(emu.regs[0xF], 0);
+	let emu = run_with(Mode::XoChip, QuirksPreset::Modern, &ins);
+	assert_eq!((emu.pixel(0, 0), emu.pixel(2, 0)), (3, 1));
+	assert_eq!(emu.regs[0xF], 1);
+}
+
+#[test]
+fn skip_long_index() {
+	// v1 := 1; if v1 != 1 then F000; 1234
+	let ins: [u16; 4] = [0x6101, 0x3101, 0xF000, 0x1234];
+	let program: Vec<u8> = ins.iter().flat_map(|ins| ins.to_be_bytes()).collect();
+
+	// `F000` is 2 bytes long before XO-CHIP, so the execution and the listing land on `1234`
+	for (mode, pc, lands) in [(Mode::SuperChip, 0x206, true), (Mode::XoChip, 0x208, false)] {
+		let mut emu = new_emu(mode, QuirksPreset::Modern, &ins);
+		steps(&mut emu, 2);
+		assert_eq!(emu.pc, pc);
+
+		let lines = disasm::disassemble(&program, mode);
+		let code = lines
+			.iter()
+			.any(|line| line.addr == 0x206 && line.kind == disasm::LineKind::Code);
+		assert_eq!(code, lands);
+	}
+}
+
+#[test]
+fn unknown_opcodes() {
+	// SUPER-CHIP and XO-CHIP instructions are unknown to the older modes
+	let emu = run_with(Mode::Chip8, QuirksPreset::CosmacVip, &[0xF030]);
+	assert_eq!(
+		emu.errors.last(),
+		Some(&EmuError::UnknownOpcode {
+			pc: 0x200,
+			op: 0xF030
+		})
+	);
+	let emu = run(&[0xF000, 0x1234]);
+	assert_eq!(emu.errors.iter().count(), 1);
+
+	// 0nnn calls a machine code routine on the COSMAC VIP and is ignored
+	let emu = run_with(Mode::Chip8, QuirksPreset::CosmacVip, &[0x00FF]);
+	assert!(!emu.hires);
+	assert!(emu.errors.is_empty());
+}
+
+/// Test ROMs and their expected screens listed in `roms/tests/screens.txt`
+#[test]
+fn test_roms() {
+	let dir = Path::new(env!("CARGO_MANIFEST_DIR")).join("roms/tests");
+	let list = fs::read_to_string(dir.join("screens.txt")).unwrap();
+
+	for line in list.lines().map(str::trim) {
+		if line.is_empty() || line.starts_with('#') {
+			continue;
+		}
+
+		let fields: Vec<&str> = line.split_whitespace().collect();
+		let &[file, mode, quirks, frames, hash] = fields.as_slice() else {
+			panic!("invalid test ROM entry \"{line}\"");
+		};
+
+		// Third-party ROMs aren't bundled, they have to be put into the directory first
+		let path = dir.join(file);
+		if !path.exists() {
+			eprintln!("skipping missing test ROM {file}");
+			continue;
+		}
+
+		let mode = Mode::from_name(mode).unwrap();
+		let preset = QuirksPreset::from_name(quirks).unwrap();
+		let mut emu = new_emu(mode, preset, &[]);
+		emu.load(&read_rom(&path).unwrap());
+		for _ in 0..frames.parse::<u32>().unwrap() {
+			emu.tick();
+		}
+
+		assert_eq!(format!("{:016x}", emu.screen_hash()), hash, "{file}");
+	}
+}
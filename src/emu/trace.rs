@@ -0,0 +1,200 @@
+use std::{
+	fmt,
+	io::{self, Read, Write},
+	ops::RangeInclusive,
+};
+
+use super::{Emu, Mode, Op, Registers, SnapshotReader, SnapshotWriter};
+
+/// Which instructions get traced
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct TraceFilter {
+	/// Trace only instructions at these addresses
+	pub addrs: Option<RangeInclusive<u16>>,
+	/// Trace only instructions executed during these frames
+	pub frames: Option<RangeInclusive<u32>>,
+}
+impl TraceFilter {
+	pub fn matches(&self, frame: u32, pc: u16) -> bool {
+		self.addrs.as_ref().is_none_or(|addrs| addrs.contains(&pc))
+			&& self
+				.frames
+				.as_ref()
+				.is_none_or(|frames| frames.contains(&frame))
+	}
+}
+
+/// Machine state right before the instruction is executed
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TraceRecord {
+	pub frame: u32,
+	pub pc: u16,
+	pub ins: u16,
+	pub mode: Mode,
+	pub index: u16,
+	pub regs: [u8; Registers::COUNT],
+	pub delay_timer: u8,
+	pub sound_timer: u8,
+	/// Whether the instruction has caused an emulator error
+	pub error: bool,
+}
+impl TraceRecord {
+	/// Size of the binary record in bytes
+	pub const SIZE: usize = 4 + 2 + 2 + 1 + 2 + Registers::COUNT + 1 + 1 + 1;
+
+	pub fn new(emu: &Emu) -> Self {
+		let (hi, lo) = emu.cur_ins;
+		let mut regs = [0; Registers::COUNT];
+		regs.copy_from_slice(&emu.regs);
+
+		Self {
+			frame: emu.frame,
+			pc: emu.pc,
+			ins: u16::from_be_bytes([hi, lo]),
+			mode: emu.mode,
+			index: emu.index,
+			regs,
+			delay_timer: emu.delay_timer,
+			sound_timer: emu.sound_timer,
+			error: false,
+		}
+	}
+
+	fn write(&self, w: &mut SnapshotWriter) {
+		w.u32(self.frame);
+		w.u16(self.pc);
+		w.u16(self.ins);
+		w.u8(self.mode as u8);
+		w.u16(self.index);
+		w.slice(&self.regs);
+		w.u8(self.delay_timer);
+		w.u8(self.sound_timer);
+		w.bool(self.error);
+	}
+	fn read(r: &mut SnapshotReader) -> Option<Self> {
+		Some(Self {
+			frame: r.u32()?,
+			pc: r.u16()?,
+			ins: r.u16()?,
+			mode: Mode::from_index(r.u8()?)?,
+			index: r.u16()?,
+			regs: r.array()?,
+			delay_timer: r.u8()?,
+			sound_timer: r.u8()?,
+			error: r.bool()?,
+		})
+	}
+}
+/// Text line of the record
+impl fmt::Display for TraceRecord {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		let op = Op::decode(self.ins, self.mode).to_string();
+
+		write!(
+			f,
+			"{:>6}  {:04X}  {:04X}  {op:<24}  I={:04X} V=",
+			self.frame, self.pc, self.ins, self.index
+		)?;
+		for v in self.regs {
+			write!(f, "{v:02X}")?;
+		}
+		write!(
+			f,
+			" DT={:02X} ST={:02X}",
+			self.delay_timer, self.sound_timer
+		)?;
+
+		if self.error {
+			write!(f, "  # error")?;
+		}
+		Ok(())
+	}
+}
+
+/// Header of the binary trace file
+const MAGIC: &[u8; 4] = b"CMXT";
+const VERSION: u8 = 1;
+
+/// Writes trace records in the compact binary format
+pub struct TraceWriter<W: Write> {
+	writer: W,
+}
+impl<W: Write> TraceWriter<W> {
+	pub fn new(mut writer: W) -> io::Result<Self> {
+		writer.write_all(MAGIC)?;
+		writer.write_all(&[VERSION])?;
+		Ok(Self { writer })
+	}
+
+	/// Write and remove all the records
+	pub fn write(&mut self, records: &mut Vec<TraceRecord>) -> io::Result<()> {
+		let mut w = SnapshotWriter::default();
+		for record in records.drain(..) {
+			record.write(&mut w);
+		}
+		self.writer.write_all(&w.bytes)
+	}
+	pub fn flush(&mut self) -> io::Result<()> {
+		self.writer.flush()
+	}
+}
+
+/// Read all records of the binary trace
+pub fn read_trace(mut reader: impl Read) -> io::Result<Vec<TraceRecord>> {
+	let mut bytes = Vec::new();
+	reader.read_to_end(&mut bytes)?;
+
+	let invalid = |msg| io::Error::new(io::ErrorKind::InvalidData, msg);
+
+	let mut r = SnapshotReader::new(&bytes);
+	if r.array() != Some(*MAGIC) {
+		return Err(invalid("not a trace file"));
+	}
+	if r.u8() != Some(VERSION) {
+		return Err(invalid("unsupported trace version"));
+	}
+
+	let data = r.rest();
+	if !data.len().is_multiple_of(TraceRecord::SIZE) {
+		return Err(invalid("trace is truncated"));
+	}
+
+	let mut r = SnapshotReader::new(data);
+	let mut records = Vec::with_capacity(data.len() / TraceRecord::SIZE);
+	while let Some(record) = TraceRecord::read(&mut r) {
+		records.push(record);
+	}
+
+	Ok(records)
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn round_trip() {
+		let mut emu = Emu::default();
+		// v0 := 1; 5F1F is an unknown instruction
+		emu.load(&[0x60, 0x01, 0x5F, 0x1F]);
+		emu.trace_filter = Some(TraceFilter {
+			addrs: Some(0x200..=0x202),
+			frames: None,
+		});
+		emu.step();
+		emu.step();
+		emu.step();
+
+		let mut bytes = Vec::new();
+		let mut writer = TraceWriter::new(&mut bytes).unwrap();
+		writer.write(&mut emu.trace).unwrap();
+		assert!(emu.trace.is_empty());
+
+		let records = read_trace(bytes.as_slice()).unwrap();
+		assert_eq!(records.len(), 2);
+		assert_eq!((records[0].pc, records[0].ins), (0x200, 0x6001));
+		assert!(!records[0].error);
+		assert_eq!(records[1].regs[0], 1);
+		assert!(records[1].error);
+	}
+}
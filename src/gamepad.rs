@@ -0,0 +1,119 @@
+/// Gamepad button, in the order of the standard layout of the browser Gamepad API
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum GamepadButton {
+	South,
+	East,
+	West,
+	North,
+	L1,
+	R1,
+	L2,
+	R2,
+	Select,
+	Start,
+	/// Left stick press
+	L3,
+	/// Right stick press
+	R3,
+	Up,
+	Down,
+	Left,
+	Right,
+}
+impl GamepadButton {
+	#[rustfmt::skip]
+	pub const ALL: [Self; 16] = [
+		Self::South, Self::East, Self::West, Self::North,
+		Self::L1, Self::R1, Self::L2, Self::R2,
+		Self::Select, Self::Start, Self::L3, Self::R3,
+		Self::Up, Self::Down, Self::Left, Self::Right,
+	];
+
+	/// Bit of the button in the pressed buttons mask
+	pub fn mask(self) -> u32 {
+		1 << self as u32
+	}
+	/// Name used in keymap files, e.g. `PadSouth` or `PadUp`
+	pub fn name(self) -> String {
+		format!("Pad{self:?}")
+	}
+	/// Button with the name, case insensitive
+	pub fn from_name(name: &str) -> Option<Self> {
+		Self::ALL
+			.into_iter()
+			.find(|button| button.name().eq_ignore_ascii_case(name))
+	}
+
+	#[cfg(not(target_arch = "wasm32"))]
+	fn gilrs_button(self) -> gilrs::Button {
+		use gilrs::Button;
+
+		match self {
+			Self::South => Button::South,
+			Self::East => Button::East,
+			Self::West => Button::West,
+			Self::North => Button::North,
+			Self::L1 => Button::LeftTrigger,
+			Self::R1 => Button::RightTrigger,
+			Self::L2 => Button::LeftTrigger2,
+			Self::R2 => Button::RightTrigger2,
+			Self::Select => Button::Select,
+			Self::Start => Button::Start,
+			Self::L3 => Button::LeftThumb,
+			Self::R3 => Button::RightThumb,
+			Self::Up => Button::DPadUp,
+			Self::Down => Button::DPadDown,
+			Self::Left => Button::DPadLeft,
+			Self::Right => Button::DPadRight,
+		}
+	}
+}
+
+/// Connected gamepads
+/// Uses gilrs on native builds and the browser Gamepad API on wasm
+pub struct Gamepads {
+	#[cfg(not(target_arch = "wasm32"))]
+	gilrs: Option<gilrs::Gilrs>,
+}
+impl Gamepads {
+	pub fn new() -> Self {
+		#[cfg(not(target_arch = "wasm32"))]
+		let gilrs = match gilrs::Gilrs::new() {
+			Ok(gilrs) => Some(gilrs),
+			Err(e) => {
+				eprintln!("ERROR: failed to initialize gamepads: {e}");
+				None
+			}
+		};
+
+		Self {
+			#[cfg(not(target_arch = "wasm32"))]
+			gilrs,
+		}
+	}
+
+	/// Mask of the buttons pressed on any of the gamepads, see [`GamepadButton::mask`]
+	#[cfg(not(target_arch = "wasm32"))]
+	pub fn pressed(&mut self) -> u32 {
+		let Some(gilrs) = &mut self.gilrs else {
+			return 0;
+		};
+
+		// Gamepads state is updated by the events
+		while gilrs.next_event().is_some() {}
+
+		let mut mask = 0;
+		for (_, gamepad) in gilrs.gamepads() {
+			for button in GamepadButton::ALL {
+				if gamepad.is_pressed(button.gilrs_button()) {
+					mask |= button.mask();
+				}
+			}
+		}
+		mask
+	}
+	#[cfg(target_arch = "wasm32")]
+	pub fn pressed(&mut self) -> u32 {
+		unsafe { crate::native::gamepad_buttons() }
+	}
+}
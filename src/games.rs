@@ -1,36 +1,98 @@
+use std::{borrow::Cow, path::PathBuf};
+
+use crate::emu::{Mode, QuirksPreset};
+
 macro_rules! game {
-	($name:expr) => {
+	($name:expr, $mode:ident, $quirks:ident) => {
 		GameInfo {
-			title: concat!($name, ".ch8"),
-			desc: include_str!(concat!("../roms/", $name, ".txt")),
-			bytes: include_bytes!(concat!("../roms/", $name, ".ch8")),
+			title: Cow::Borrowed(concat!($name, ".ch8")),
+			desc: Cow::Borrowed(include_str!(concat!("../roms/", $name, ".txt"))),
+			bytes: Cow::Borrowed(include_bytes!(concat!("../roms/", $name, ".ch8"))),
+			mode: Mode::$mode,
+			quirks: QuirksPreset::$quirks,
+			path: None,
 		}
 	};
 }
 
 /// Game info
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct GameInfo {
 	/// Game name
-	pub title: &'static str,
+	pub title: Cow<'static, str>,
 	/// Game description
-	pub desc: &'static str,
+	pub desc: Cow<'static, str>,
 	/// Game program data
-	pub bytes: &'static [u8],
+	pub bytes: Cow<'static, [u8]>,
+	/// Instruction set the game was written for
+	pub mode: Mode,
+	/// Quirks profile the game was written for
+	pub quirks: QuirksPreset,
+	/// Path of the ROM file, `None` for built-in games
+	pub path: Option<PathBuf>,
 }
 
 pub const GAMES: &[GameInfo] = &[
-	game!("tracer"),
-	game!("langtons-ant"),
-	game!("outlaw"),
-	game!("vers"),
-	game!("rush-hour"),
-	game!("blinky"),
-	game!("worm-v4"),
-	game!("tic-tac-toe"),
-	game!("breakout"),
-	game!("landing"),
-	game!("puzzle"),
-	game!("space-invaders"),
-	game!("pong"),
+	game!("tracer", XoChip, Modern),
+	game!("langtons-ant", XoChip, Modern),
+	game!("outlaw", XoChip, Modern),
+	game!("vers", Chip8, Chip48),
+	game!("rush-hour", Chip8, Modern),
+	game!("blinky", Chip8, Chip48),
+	game!("worm-v4", Chip8, Modern),
+	game!("tic-tac-toe", Chip8, CosmacVip),
+	game!("breakout", Chip8, CosmacVip),
+	game!("landing", Chip8, CosmacVip),
+	game!("puzzle", Chip8, CosmacVip),
+	game!("space-invaders", Chip8, Chip48),
+	game!("pong", Chip8, CosmacVip),
 ];
+
+/// ROM file extensions listed in the cartridge picker
+pub const ROM_EXTENSIONS: &[&str] = &["ch8", "sc8", "xo8", "8o"];
+
+/// List all ROMs in the directory
+/// Descriptions are read from sibling `.txt` files, ROMs that can't be read are skipped
+#[cfg(not(target_arch = "wasm32"))]
+pub fn scan_roms(dir: &std::path::Path) -> std::io::Result<Vec<GameInfo>> {
+	use crate::rom::read_rom;
+
+	let mut games = Vec::new();
+
+	for entry in std::fs::read_dir(dir)? {
+		let path = entry?.path();
+		let Some(ext) = path.extension().map(|ext| ext.to_string_lossy()) else {
+			continue;
+		};
+		if !ROM_EXTENSIONS.contains(&ext.as_ref()) {
+			continue;
+		}
+
+		let bytes = match read_rom(&path) {
+			Ok(bytes) => bytes,
+			Err(e) => {
+				e.print(Some(&path));
+				continue;
+			}
+		};
+
+		games.push(GameInfo {
+			title: path
+				.file_name()
+				.unwrap_or_default()
+				.to_string_lossy()
+				.into_owned()
+				.into(),
+			desc: std::fs::read_to_string(path.with_extension("txt"))
+				.unwrap_or_default()
+				.into(),
+			bytes: bytes.into(),
+			mode: Mode::from_extension(&ext).unwrap_or_default(),
+			quirks: QuirksPreset::default(),
+			path: Some(path),
+		});
+	}
+
+	games.sort_by(|a, b| a.title.cmp(&b.title));
+	Ok(games)
+}
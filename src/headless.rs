@@ -0,0 +1,353 @@
+use std::{
+	fmt::Write,
+	fs::File,
+	io::{self, BufReader, BufWriter, Write as _},
+	path::Path,
+	process::exit,
+};
+
+use crate::{
+	app::load_rom,
+	cli::Cli,
+	emu::{Emu, Hit, TraceWriter, asm, disasm, read_trace},
+	games::GAMES,
+	movie::{Movie, MovieTape, TapeMode},
+};
+
+/// Format of the screen dump
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ScreenDump {
+	/// Text where unlit pixels are `.` and lit ones are `#`, `+` or `@` depending on the planes
+	Ascii,
+	/// Plain (`P1`) portable bitmap
+	Pbm,
+	/// Hex hash of the screen pixels, see [`Emu::screen_hash`]
+	Hash,
+}
+impl ScreenDump {
+	pub fn from_name(name: &str) -> Option<Self> {
+		match name {
+			"ascii" => Some(Self::Ascii),
+			"pbm" => Some(Self::Pbm),
+			"hash" => Some(Self::Hash),
+			_ => None,
+		}
+	}
+}
+
+/// Number of frames to run if not specified, one second
+pub const DEFAULT_FRAMES: u32 = 60;
+
+/// Load the ROM, print its disassembly and exit
+pub fn print_disasm(cli: &Cli) -> ! {
+	let mut emu = Emu::default();
+	if let Some(e) = load_rom(&mut emu, cli) {
+		e.print(cli.rom_path.as_ref());
+		if !e.is_warning() {
+			exit(1);
+		}
+	}
+
+	for line in disasm::disassemble(&emu.program, emu.mode) {
+		println!("{line}");
+	}
+
+	exit(0);
+}
+
+/// Assemble the Octo source into a `.ch8` file next to it and exit
+pub fn assemble(path: &Path) -> ! {
+	let source = match std::fs::read_to_string(path) {
+		Ok(source) => source,
+		Err(e) => {
+			eprintln!("ERROR: {}: {e}", path.display());
+			exit(1);
+		}
+	};
+
+	let program = match asm::assemble(&source) {
+		Ok(program) => program,
+		Err(e) => {
+			eprintln!("ERROR: {}: {e}", path.display());
+			exit(1);
+		}
+	};
+
+	let out = path.with_extension("ch8");
+	if let Err(e) = std::fs::write(&out, &program) {
+		eprintln!("ERROR: {}: {e}", out.display());
+		exit(1);
+	}
+
+	println!("{}: {} bytes", out.display(), program.len());
+	exit(0);
+}
+
+/// Create the trace file requested by `--trace` and enable tracing in the emulator
+/// Exits if the file can't be created
+pub fn open_trace(cli: &Cli, emu: &mut Emu) -> Option<TraceFile> {
+	let path = cli.trace.as_ref()?;
+
+	let writer = File::create(path).and_then(|file| TraceWriter::new(BufWriter::new(file)));
+	match writer {
+		Ok(writer) => {
+			emu.trace_filter = Some(cli.trace_filter.clone());
+			Some(TraceFile { writer })
+		}
+		Err(e) => {
+			eprintln!("ERROR: failed to create trace file {}: {e}", path.display());
+			exit(1);
+		}
+	}
+}
+
+/// Start playing the movie requested by `--play` or recording the one requested by `--record`
+/// Exits if the movie can't be played
+pub fn open_movie(cli: &Cli, emu: &mut Emu) -> Option<MovieTape> {
+	if let Some(path) = &cli.play {
+		let tape = Movie::read(path).and_then(|movie| MovieTape::play(movie, emu));
+		return match tape {
+			Ok(mut tape) => {
+				// Edits made in the piano roll are saved back
+				tape.path = Some(path.clone());
+				Some(tape)
+			}
+			Err(e) => {
+				eprintln!("ERROR: {}: {e}", path.display());
+				exit(1);
+			}
+		};
+	}
+
+	let path = cli.record.clone()?;
+	Some(MovieTape::record(emu, Some(path)))
+}
+
+/// Trace file being written
+pub struct TraceFile {
+	writer: TraceWriter<BufWriter<File>>,
+}
+impl TraceFile {
+	/// Move the recorded instructions from the emulator into the file
+	/// Tracing is stopped if the file can't be written
+	pub fn write(&mut self, emu: &mut Emu) -> io::Result<()> {
+		// Flush every time, the app may be closed at any moment
+		let result = self
+			.writer
+			.write(&mut emu.trace)
+			.and_then(|_| self.writer.flush());
+		if let Err(e) = &result {
+			eprintln!("ERROR: failed to write trace: {e}");
+			emu.trace_filter = None;
+			emu.trace.clear();
+		}
+		result
+	}
+}
+
+/// Print the binary trace file as text and exit
+pub fn print_trace(path: &Path) -> ! {
+	let records = File::open(path).and_then(|file| read_trace(BufReader::new(file)));
+	let records = match records {
+		Ok(records) => records,
+		Err(e) => {
+			eprintln!("ERROR: {}: {e}", path.display());
+			exit(1);
+		}
+	};
+
+	// Traces are long and often piped into `head`, so stop quietly once stdout is closed
+	let mut out = io::stdout().lock();
+	for record in records {
+		if writeln!(out, "{record}").is_err() {
+			break;
+		}
+	}
+	exit(0);
+}
+
+/// Run the ROM without a window and audio, print the requested results and exit
+/// The run stops early when a debugger breakpoint or watchpoint is hit
+/// Exits with code 1 if the emulator has encountered an error
+pub fn run(cli: &Cli) -> ! {
+	let mut emu = Emu::default();
+	if let Some(e) = load_rom(&mut emu, cli) {
+		e.print(cli.rom_path.as_ref());
+		if !e.is_warning() {
+			exit(1);
+		}
+	}
+	// Always the same seed, so runs are reproducible
+	emu.set_seed(cli.seed.unwrap_or_default());
+	emu.set_speed(cli.speed.unwrap_or(Emu::DEFAULT_SPEED));
+	emu.ips = cli.ips.unwrap_or(Emu::DEFAULT_IPS);
+	emu.strict = cli.strict;
+	emu.memory_policy = cli.memory_policy;
+	emu.debugger.breakpoints = cli.breakpoints.clone();
+	emu.debugger.watchpoints = cli.watchpoints.clone();
+	let mut trace = open_trace(cli, &mut emu);
+	// There is no input to record without a window
+	let mut tape = open_movie(cli, &mut emu).filter(|tape| tape.mode == TapeMode::Play);
+
+	let frames = cli
+		.frames
+		.or(tape.as_ref().map(|tape| tape.movie.frames.len() as u32))
+		.unwrap_or(DEFAULT_FRAMES);
+	for _ in 0..frames {
+		if emu.exited {
+			break;
+		}
+
+		match &mut tape {
+			Some(tape) => tape.tick(&mut emu),
+			None => emu.tick(),
+		}
+
+		if let Some(file) = &mut trace
+			&& file.write(&mut emu).is_err()
+		{
+			trace = None;
+		}
+
+		// Nobody can resume the run, so stop at the first hit
+		if let Some(hit) = emu.debugger.hit {
+			// Errors are printed below anyway
+			if !matches!(hit, Hit::Error(_)) {
+				eprintln!("{hit} on frame {}", emu.frame);
+			}
+			break;
+		}
+	}
+
+	if let Some(format) = cli.screen {
+		print!("{}", dump_screen(&emu, format));
+	}
+	if cli.regs {
+		print!("{}", dump_regs(&emu));
+	}
+
+	if !emu.errors.is_empty() {
+		for e in emu.errors.iter() {
+			eprintln!("ERROR: {e}");
+		}
+		exit(1);
+	}
+
+	exit(0);
+}
+
+/// Golden screen hashes of the bundled games checked by `--verify`
+const VERIFY_HASHES: &str = include_str!("../roms/verify.txt");
+/// Frames at which the screens of the bundled games are checked
+const VERIFY_CHECKPOINTS: &[u32] = &[60, 300, 900];
+/// Keys pressed in turns during the verification, each for the first half of `VERIFY_KEY_FRAMES`
+const VERIFY_KEYS: &[u8] = &[5, 6, 4, 8, 2, 0xA, 1, 7];
+const VERIFY_KEY_FRAMES: u32 = 30;
+
+/// Run every bundled game with the scripted input and compare its screens with the golden ones
+/// Exits with code 1 on a mismatch
+pub fn verify() -> ! {
+	let golden: Vec<(&str, u32, &str)> = VERIFY_HASHES
+		.lines()
+		.map(str::trim)
+		.filter(|line| !line.is_empty() && !line.starts_with('#'))
+		.filter_map(|line| {
+			let mut fields = line.split_whitespace();
+			let title = fields.next()?;
+			let frame = fields.next()?.parse().ok()?;
+			Some((title, frame, fields.next()?))
+		})
+		.collect();
+
+	let last_frame = VERIFY_CHECKPOINTS.iter().copied().max().unwrap_or_default();
+	let mut failed = 0;
+
+	println!("{:<20} {:>5}  {:<16}  RESULT", "GAME", "FRAME", "HASH");
+	for game in GAMES {
+		let mut emu = Emu {
+			mode: game.mode,
+			quirks: game.quirks.quirks(),
+			..Default::default()
+		};
+		emu.set_seed(0);
+		emu.load(&game.bytes);
+
+		for frame in 0..=last_frame {
+			if VERIFY_CHECKPOINTS.contains(&frame) {
+				let hash = format!("{:016x}", emu.screen_hash());
+				let expected = golden
+					.iter()
+					.find(|(title, f, _)| *title == game.title && *f == frame)
+					.map(|(_, _, hash)| *hash);
+
+				let result = match expected {
+					Some(expected) if expected == hash => "ok".to_string(),
+					Some(expected) => format!("FAIL, expected {expected}"),
+					None => "FAIL, no golden hash".to_string(),
+				};
+				if expected != Some(hash.as_str()) {
+					failed += 1;
+				}
+				println!("{:<20} {frame:>5}  {hash}  {result}", game.title);
+			}
+
+			let key = VERIFY_KEYS[(frame / VERIFY_KEY_FRAMES) as usize % VERIFY_KEYS.len()];
+			match frame % VERIFY_KEY_FRAMES {
+				0 => emu.set_pressed_key(key, true, true),
+				f if f == VERIFY_KEY_FRAMES / 2 => emu.set_pressed_key(key, false, false),
+				_ => (),
+			}
+			emu.tick();
+		}
+	}
+
+	if failed > 0 {
+		eprintln!("ERROR: {failed} screens don't match the golden ones");
+		exit(1);
+	}
+	exit(0);
+}
+
+pub fn dump_screen(emu: &Emu, format: ScreenDump) -> String {
+	if format == ScreenDump::Hash {
+		return format!("{:016x}\n", emu.screen_hash());
+	}
+
+	let (width, height) = (emu.screen_width(), emu.screen_height());
+	let mut out = String::new();
+
+	if format == ScreenDump::Pbm {
+		let _ = writeln!(out, "P1\n{width} {height}");
+	}
+
+	for y in 0..height {
+		for x in 0..width {
+			let planes = emu.pixel(x, y);
+			let ch = match format {
+				ScreenDump::Ascii => b".#+@"[planes as usize & 0b11] as char,
+				ScreenDump::Pbm if planes == 0 => '0',
+				ScreenDump::Pbm | ScreenDump::Hash => '1',
+			};
+			out.push(ch);
+		}
+		out.push('\n');
+	}
+
+	out
+}
+
+pub fn dump_regs(emu: &Emu) -> String {
+	let mut out = String::new();
+
+	let _ = writeln!(
+		out,
+		"PC={:04X} I={:04X} SP={:X} DT={:02X} ST={:02X}",
+		emu.pc, emu.index, emu.sp, emu.delay_timer, emu.sound_timer
+	);
+	for (i, value) in emu.regs.iter().enumerate() {
+		let sep = if i % 8 == 7 { '\n' } else { ' ' };
+		let _ = write!(out, "V{i:X}={value:02X}{sep}");
+	}
+
+	out
+}
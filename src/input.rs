@@ -2,7 +2,7 @@ use std::collections::HashSet;
 
 use miniquad::{CursorIcon, KeyCode, MouseButton, window};
 
-use crate::math::Point;
+use crate::{gamepad::GamepadButton, math::Point};
 
 bitflags::bitflags! {
 	///
@@ -10,6 +10,7 @@ bitflags::bitflags! {
 	pub struct InputConsume: u8 {
 		const VALVE = 1 << 0;
 		const BOARD_ANIM = 1 << 1;
+		const PICKER = 1 << 2;
 	}
 }
 
@@ -32,6 +33,11 @@ pub struct Input {
 	pub keys_pressed: HashSet<KeyCode>,
 	pub keys_just_released: HashSet<KeyCode>,
 
+	/// Masks of the gamepad buttons, see [`GamepadButton::mask`]
+	pub buttons_pressed: u32,
+	pub buttons_just_pressed: u32,
+	pub buttons_just_released: u32,
+
 	pub consumed_by: InputConsume,
 	/// Cursor icon to apply at the frame end
 	pub cursor_icon: CursorIcon,
@@ -53,6 +59,10 @@ impl Default for Input {
 			keys_pressed: HashSet::default(),
 			keys_just_released: HashSet::default(),
 
+			buttons_pressed: 0,
+			buttons_just_pressed: 0,
+			buttons_just_released: 0,
+
 			consumed_by: InputConsume::default(),
 			cursor_icon: CursorIcon::Default,
 			prev_cursor_icon: CursorIcon::Default,
@@ -75,6 +85,8 @@ impl Input {
 		self.mouse_just_released = false;
 		self.key_just_pressed = false;
 		self.keys_just_released.clear();
+		self.buttons_just_pressed = 0;
+		self.buttons_just_released = 0;
 	}
 
 	/// Returns whether the mouse button is currently down
@@ -111,6 +123,25 @@ impl Input {
 		!self.is_consumed() && self.keys_just_released.contains(&key)
 	}
 
+	/// Update the gamepad buttons with the currently pressed ones
+	pub fn set_buttons_pressed(&mut self, mask: u32) {
+		self.buttons_just_pressed |= mask & !self.buttons_pressed;
+		self.buttons_just_released |= self.buttons_pressed & !mask;
+		self.buttons_pressed = mask;
+	}
+	/// Returns whether the gamepad button is currently down
+	pub fn button_is_pressed(&self, button: GamepadButton) -> bool {
+		!self.is_consumed() && self.buttons_pressed & button.mask() > 0
+	}
+	/// Returns whether the gamepad button was pressed for a single frame
+	pub fn button_just_pressed(&self, button: GamepadButton) -> bool {
+		!self.is_consumed() && self.buttons_just_pressed & button.mask() > 0
+	}
+	/// Returns whether the gamepad button was released
+	pub fn button_just_released(&self, button: GamepadButton) -> bool {
+		!self.is_consumed() && self.buttons_just_released & button.mask() > 0
+	}
+
 	pub fn set_cur_mouse_transform(&mut self, offset: Point, scale: f32) {
 		self.mouse_pos = (self.mouse_pos - offset) / scale;
 	}
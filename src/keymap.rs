@@ -0,0 +1,172 @@
+use miniquad::KeyCode;
+
+use crate::{
+	config::{ConfigError, parse_config},
+	emu::Emu,
+	gamepad::GamepadButton,
+};
+
+#[cfg(not(target_arch = "wasm32"))]
+use std::path::Path;
+
+/// Keys which can be used in keymaps, named as in [`KeyCode`]
+#[rustfmt::skip]
+const KEY_CODES: &[KeyCode] = {
+	use KeyCode::*;
+	&[
+		Space, Apostrophe, Comma, Minus, Period, Slash, Semicolon, Equal,
+		Key0, Key1, Key2, Key3, Key4, Key5, Key6, Key7, Key8, Key9,
+		A, B, C, D, E, F, G, H, I, J, K, L, M, N, O, P, Q, R, S, T, U, V, W, X, Y, Z,
+		LeftBracket, Backslash, RightBracket, GraveAccent, World1, World2,
+		Escape, Enter, Tab, Backspace, Insert, Delete,
+		Right, Left, Down, Up, PageUp, PageDown, Home, End,
+		CapsLock, ScrollLock, NumLock, PrintScreen, Pause,
+		F1, F2, F3, F4, F5, F6, F7, F8, F9, F10, F11, F12,
+		Kp0, Kp1, Kp2, Kp3, Kp4, Kp5, Kp6, Kp7, Kp8, Kp9,
+		KpDecimal, KpDivide, KpMultiply, KpSubtract, KpAdd, KpEnter, KpEqual,
+		LeftShift, LeftControl, LeftAlt, LeftSuper,
+		RightShift, RightControl, RightAlt, RightSuper, Menu,
+	]
+};
+
+/// Short name of the key, digits are named without the `Key` prefix
+pub fn key_name(key: KeyCode) -> String {
+	let name = format!("{key:?}");
+	match name.strip_prefix("Key") {
+		Some(digit) => digit.to_string(),
+		None => name,
+	}
+}
+/// Key with the name, case insensitive
+pub fn key_from_name(name: &str) -> Option<KeyCode> {
+	KEY_CODES.iter().copied().find(|key| {
+		key_name(*key).eq_ignore_ascii_case(name) || format!("{key:?}").eq_ignore_ascii_case(name)
+	})
+}
+
+/// Keyboard keys and gamepad buttons of each CHIP-8 key
+///
+/// Keymap files map CHIP-8 keys onto space separated keyboard keys and gamepad buttons,
+/// e.g. `5 = W Up PadSouth`. Only the listed CHIP-8 keys are changed.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Keymap {
+	pub keys: [Vec<KeyCode>; Emu::KEYS_COUNT],
+	pub buttons: [Vec<GamepadButton>; Emu::KEYS_COUNT],
+}
+impl Default for Keymap {
+	/// `1234`/`QWER`/`ASDF`/`ZXCV` block of QWERTY keyboards,
+	/// d-pad on `2`/`4`/`6`/`8` and face buttons on `5`/`A`/`B`/`F`
+	fn default() -> Self {
+		use KeyCode::*;
+
+		let mut buttons: [Vec<GamepadButton>; Emu::KEYS_COUNT] = Default::default();
+		for (key, button) in [
+			(0x2, GamepadButton::Up),
+			(0x4, GamepadButton::Left),
+			(0x6, GamepadButton::Right),
+			(0x8, GamepadButton::Down),
+			(0x5, GamepadButton::South),
+			(0xA, GamepadButton::East),
+			(0xB, GamepadButton::West),
+			(0xF, GamepadButton::North),
+		] {
+			buttons[key].push(button);
+		}
+
+		Self {
+			keys: [X, Key1, Key2, Key3, Q, W, E, A, S, D, Z, C, Key4, R, F, V].map(|key| vec![key]),
+			buttons,
+		}
+	}
+}
+impl Keymap {
+	/// Name of the user keymap file in the config directory
+	pub const FILE_NAME: &str = "keymap.conf";
+
+	/// Override keys of the CHIP-8 keys listed in the keymap file source
+	pub fn apply(&mut self, source: &str) -> Result<(), ConfigError> {
+		for entry in parse_config(source)? {
+			let key = u8::from_str_radix(entry.key.trim_start_matches("0x"), 16)
+				.ok()
+				.filter(|key| (*key as usize) < Emu::KEYS_COUNT)
+				.ok_or_else(|| entry.error(format!("unknown CHIP-8 key \"{}\"", entry.key)))?;
+
+			let mut codes = Vec::new();
+			let mut buttons = Vec::new();
+			for name in entry.value.split_whitespace() {
+				if let Some(code) = key_from_name(name) {
+					codes.push(code);
+				} else if let Some(button) = GamepadButton::from_name(name) {
+					buttons.push(button);
+				} else {
+					return Err(entry.error(format!("unknown key \"{name}\"")));
+				}
+			}
+
+			self.keys[key as usize] = codes;
+			self.buttons[key as usize] = buttons;
+		}
+
+		Ok(())
+	}
+
+	/// Default keymap with the user keymap and the ROM keymap (e.g. `game.ch8.keymap`) applied
+	/// Invalid keymap files are reported and skipped
+	#[cfg(not(target_arch = "wasm32"))]
+	pub fn load(rom_path: Option<&Path>) -> Self {
+		let mut keymap = Self::default();
+
+		let user_path = crate::config::config_dir().map(|dir| dir.join(Self::FILE_NAME));
+		let rom_path = rom_path.and_then(|path| crate::rom::rom_sibling(path, ".keymap"));
+
+		for path in [user_path, rom_path].into_iter().flatten() {
+			let Ok(source) = std::fs::read_to_string(&path) else {
+				continue;
+			};
+			if let Err(e) = keymap.apply(&source) {
+				eprintln!("ERROR: {}: {e}", path.display());
+			}
+		}
+
+		keymap
+	}
+	#[cfg(target_arch = "wasm32")]
+	pub fn load(_rom_path: Option<&std::path::Path>) -> Self {
+		Self::default()
+	}
+
+	/// Names of the keyboard keys and gamepad buttons of the CHIP-8 key separated by slashes,
+	/// e.g. `W/Up/PadSouth`
+	pub fn key_names(&self, key: u8) -> String {
+		let keys = self.keys[key as usize].iter().map(|k| key_name(*k));
+		let buttons = self.buttons[key as usize].iter().map(|b| b.name());
+		keys.chain(buttons).collect::<Vec<_>>().join("/")
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn apply() {
+		let mut keymap = Keymap::default();
+		keymap
+			.apply("# arrows\n5 = W up padsouth\n0x8 = s Down  # comment\n\nA = kp0\n")
+			.unwrap();
+
+		assert_eq!(keymap.keys[5], [KeyCode::W, KeyCode::Up]);
+		assert_eq!(keymap.buttons[5], [GamepadButton::South]);
+		assert!(keymap.buttons[8].is_empty());
+		assert_eq!(keymap.buttons[2], [GamepadButton::Up]);
+		assert_eq!(keymap.keys[8], [KeyCode::S, KeyCode::Down]);
+		assert_eq!(keymap.keys[0xA], [KeyCode::Kp0]);
+		assert_eq!(keymap.keys[1], [KeyCode::Key1]);
+		assert_eq!(keymap.key_names(5), "W/Up/PadSouth");
+
+		let e = keymap.apply("1 = 1\n2 = Foo").unwrap_err();
+		assert_eq!(e.to_string(), "line 2: unknown key \"Foo\"");
+		assert!(keymap.apply("G = A").is_err());
+		assert!(keymap.apply("5 W").is_err());
+	}
+}
@@ -2,26 +2,59 @@ mod app;
 mod assets;
 mod audio;
 mod cli;
+mod config;
+#[cfg(not(target_arch = "wasm32"))]
+mod debug_server;
 mod emu;
+mod gamepad;
 mod games;
+mod headless;
 mod input;
+mod keymap;
 mod math;
+mod movie;
 mod native;
 mod painter;
+mod palette;
+mod rewind;
+mod rom;
+mod savestate;
 mod scene;
+mod settings;
 mod state;
 mod tooltip;
 mod util;
 
-use app::App;
+use app::{App, CANVAS_HEIGHT, CANVAS_WIDTH};
 use cli::Cli;
 use miniquad::conf;
 
 fn main() {
+	let mut cli = Cli::default();
+	cli.parse();
+
+	if let Some(path) = &cli.assemble {
+		headless::assemble(path);
+	}
+	if let Some(path) = &cli.trace_text {
+		headless::print_trace(path);
+	}
+	if cli.verify {
+		headless::verify();
+	}
+	if cli.disasm {
+		headless::print_disasm(&cli);
+	}
+	if cli.headless {
+		headless::run(&cli);
+	}
+
+	let scale = cli.scale.unwrap_or(1.0);
 	let conf = conf::Conf {
 		window_title: "CHIP MAXIMATOR - by bogdanov".to_string(),
-		window_width: 700,
-		window_height: 700,
+		window_width: (CANVAS_WIDTH * scale) as i32,
+		window_height: (CANVAS_HEIGHT * scale) as i32,
+		fullscreen: cli.fullscreen,
 		platform: conf::Platform {
 			// Use X11 first because previously miniquad was crashing every time it was launched on
 			// Wayland compositor (at least on Hyprland) with Wayland first backend.
@@ -34,9 +67,6 @@ fn main() {
 		..Default::default()
 	};
 
-	let mut cli = Cli::default();
-	cli.parse();
-
 	// Set random seed
 	quad_rand::srand(native::now_millis() as u64);
 
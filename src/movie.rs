@@ -0,0 +1,512 @@
+use std::{fmt, path::PathBuf};
+
+use crate::emu::{Emu, LoadStore, MemoryPolicy, Mode, Quirks, SnapshotReader, SnapshotWriter};
+
+/// Movie error
+#[derive(Debug)]
+pub enum MovieError {
+	/// Not a movie file
+	BadMagic,
+	/// Movie was made by an incompatible version
+	Version(u16),
+	/// Movie was recorded with a different ROM
+	RomMismatch,
+	/// Movie data is truncated or damaged
+	Corrupted,
+	Io(std::io::Error),
+}
+impl fmt::Display for MovieError {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		match self {
+			Self::BadMagic => write!(f, "Not a movie"),
+			Self::Version(v) => write!(f, "Unsupported movie version {v}"),
+			Self::RomMismatch => write!(f, "Movie is for another ROM"),
+			Self::Corrupted => write!(f, "Movie is corrupted"),
+			Self::Io(e) => write!(f, "{e}"),
+		}
+	}
+}
+
+/// Input of a single 60 Hz tick, applied right before it
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct MovieFrame {
+	/// Mask of the held CHIP-8 keys, bit N is key N
+	pub keys: u16,
+	/// Key which ended the `Fx0A` wait
+	pub press: Option<u8>,
+	/// Valve speed, see [`Emu::speed`]
+	pub speed: f32,
+}
+impl MovieFrame {
+	pub fn is_key_held(&self, key: u8) -> bool {
+		self.keys & (1 << key) > 0
+	}
+}
+
+/// Recorded session: emulator configuration and input of every tick since the power-on
+///
+/// Format:
+/// - magic bytes `CMXM`
+/// - format version (u16)
+/// - hash of the ROM the movie was recorded with (u64)
+/// - LZ4 compressed body with the configuration and the frames,
+///   the speed is only stored when it changes
+#[derive(Debug, Clone, PartialEq)]
+pub struct Movie {
+	pub rom_hash: u64,
+	pub seed: u64,
+	pub mode: Mode,
+	pub quirks: Quirks,
+	pub memory_policy: MemoryPolicy,
+	pub strict: bool,
+	pub ips: u32,
+	pub frames: Vec<MovieFrame>,
+}
+impl Movie {
+	const MAGIC: &[u8; 4] = b"CMXM";
+	pub const VERSION: u16 = 1;
+
+	/// Frame flag, the `Fx0A` wait was ended before the tick
+	const FLAG_PRESS: u8 = 1 << 0;
+	/// Frame flag, the speed was changed before the tick
+	const FLAG_SPEED: u8 = 1 << 1;
+
+	/// Empty movie with the configuration of the emulator
+	pub fn new(emu: &Emu) -> Self {
+		Self {
+			rom_hash: emu.program_hash(),
+			seed: emu.seed,
+			mode: emu.mode,
+			quirks: emu.quirks,
+			memory_policy: emu.memory_policy,
+			strict: emu.strict,
+			ips: emu.ips,
+			frames: Vec::new(),
+		}
+	}
+
+	pub fn save(&self) -> Vec<u8> {
+		let mut body = SnapshotWriter::default();
+		body.u64(self.seed);
+		body.u8(self.mode as u8);
+		body.bool(self.quirks.shift_vy);
+		body.u8(self.quirks.load_store as u8);
+		body.bool(self.quirks.vf_reset);
+		body.bool(self.quirks.jump_vx);
+		body.bool(self.quirks.clip_sprites);
+		body.u8(self.memory_policy as u8);
+		body.bool(self.strict);
+		body.u32(self.ips);
+
+		body.u32(self.frames.len() as u32);
+		let mut speed = None;
+		for frame in self.frames.iter() {
+			let speed_changed = speed != Some(frame.speed);
+			speed = Some(frame.speed);
+
+			let mut flags = 0;
+			if frame.press.is_some() {
+				flags |= Self::FLAG_PRESS;
+			}
+			if speed_changed {
+				flags |= Self::FLAG_SPEED;
+			}
+
+			body.u8(flags);
+			body.u16(frame.keys);
+			if let Some(key) = frame.press {
+				body.u8(key);
+			}
+			if speed_changed {
+				body.f32(frame.speed);
+			}
+		}
+
+		let mut w = SnapshotWriter::default();
+		w.slice(Self::MAGIC);
+		w.u16(Self::VERSION);
+		w.u64(self.rom_hash);
+		w.slice(&lz4_flex::compress_prepend_size(&body.bytes));
+		w.bytes
+	}
+	pub fn load(bytes: &[u8]) -> Result<Self, MovieError> {
+		let mut r = SnapshotReader::new(bytes);
+		if r.slice(Self::MAGIC.len()) != Some(Self::MAGIC) {
+			return Err(MovieError::BadMagic);
+		}
+
+		let version = r.u16().ok_or(MovieError::Corrupted)?;
+		if version != Self::VERSION {
+			return Err(MovieError::Version(version));
+		}
+		let rom_hash = r.u64().ok_or(MovieError::Corrupted)?;
+
+		let body =
+			lz4_flex::decompress_size_prepended(r.rest()).map_err(|_| MovieError::Corrupted)?;
+		Self::read_body(rom_hash, &mut SnapshotReader::new(&body)).ok_or(MovieError::Corrupted)
+	}
+	fn read_body(rom_hash: u64, r: &mut SnapshotReader) -> Option<Self> {
+		let seed = r.u64()?;
+		let mode = Mode::from_index(r.u8()?)?;
+		let quirks = Quirks {
+			shift_vy: r.bool()?,
+			load_store: match r.u8()? {
+				0 => LoadStore::Increment,
+				1 => LoadStore::IncrementX,
+				2 => LoadStore::Keep,
+				_ => return None,
+			},
+			vf_reset: r.bool()?,
+			jump_vx: r.bool()?,
+			clip_sprites: r.bool()?,
+		};
+		let memory_policy = match r.u8()? {
+			0 => MemoryPolicy::Wrap,
+			1 => MemoryPolicy::Fault,
+			_ => return None,
+		};
+		let strict = r.bool()?;
+		let ips = r.u32()?;
+
+		let count = r.u32()? as usize;
+		let mut frames = Vec::new();
+		let mut speed = Emu::DEFAULT_SPEED;
+		for _ in 0..count {
+			let flags = r.u8()?;
+			let keys = r.u16()?;
+			let press = match flags & Self::FLAG_PRESS {
+				0 => None,
+				_ => Some(r.u8()? % Emu::KEYS_COUNT as u8),
+			};
+			if flags & Self::FLAG_SPEED > 0 {
+				speed = r.f32()?;
+			}
+
+			frames.push(MovieFrame { keys, press, speed });
+		}
+
+		Some(Self {
+			rom_hash,
+			seed,
+			mode,
+			quirks,
+			memory_policy,
+			strict,
+			ips,
+			frames,
+		})
+	}
+
+	/// Read the movie file
+	#[cfg(not(target_arch = "wasm32"))]
+	pub fn read(path: &std::path::Path) -> Result<Self, MovieError> {
+		Self::load(&std::fs::read(path).map_err(MovieError::Io)?)
+	}
+
+	/// Configure the emulator as it was when the recording started and restart it
+	/// Nothing is changed if the emulator has a different ROM
+	pub fn setup(&self, emu: &mut Emu) -> Result<(), MovieError> {
+		if emu.program_hash() != self.rom_hash {
+			return Err(MovieError::RomMismatch);
+		}
+
+		emu.mode = self.mode;
+		emu.quirks = self.quirks;
+		emu.memory_policy = self.memory_policy;
+		emu.strict = self.strict;
+		emu.ips = self.ips;
+		emu.set_seed(self.seed);
+		emu.setup();
+		Ok(())
+	}
+}
+
+/// What the tape does on each tick
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TapeMode {
+	/// Frames already in the movie are played, then input of every tick is appended to it
+	Record,
+	/// Input of every tick is taken from the movie
+	Play,
+}
+
+/// Movie attached to the running emulator
+pub struct MovieTape {
+	pub movie: Movie,
+	pub mode: TapeMode,
+	/// File the movie is saved into, only recorded movies are saved
+	pub path: Option<PathBuf>,
+	/// Whether the playback is finished or the recording went out of sync with the emulator
+	pub ended: bool,
+
+	/// Number of recorded or played ticks
+	pos: usize,
+	/// `Fx0A` register the emulator was waiting on after the last tick
+	wait: Option<u8>,
+}
+impl MovieTape {
+	/// Start recording the emulator from its current state, which should be the power-on one
+	pub fn record(emu: &Emu, path: Option<PathBuf>) -> Self {
+		Self {
+			movie: Movie::new(emu),
+			mode: TapeMode::Record,
+			path,
+			ended: false,
+			pos: 0,
+			wait: emu.wait_for_keypress,
+		}
+	}
+	/// Restart the emulator with the movie configuration and start playing it
+	pub fn play(movie: Movie, emu: &mut Emu) -> Result<Self, MovieError> {
+		movie.setup(emu)?;
+
+		Ok(Self {
+			movie,
+			mode: TapeMode::Play,
+			path: None,
+			ended: false,
+			pos: 0,
+			wait: None,
+		})
+	}
+
+	/// Whether the input of the upcoming tick is taken from the movie
+	pub fn is_playing(&self) -> bool {
+		!self.ended && self.pos < self.movie.frames.len()
+	}
+	/// Number of recorded or played ticks, index of the upcoming frame
+	pub fn pos(&self) -> usize {
+		self.pos
+	}
+
+	/// Record or play the input of the upcoming tick and run it
+	/// The tape ends if the emulator was rewound, reset or loaded from a save state in between,
+	/// the tick itself is run anyway
+	pub fn tick(&mut self, emu: &mut Emu) {
+		if !self.ended {
+			let finished = self.mode == TapeMode::Play && self.pos >= self.movie.frames.len();
+			self.ended = finished || emu.frame as usize != self.pos;
+		}
+
+		if !self.ended {
+			match self.movie.frames.get(self.pos) {
+				Some(frame) => Self::apply_frame(emu, frame),
+				None => self.record_frame(emu),
+			}
+			self.pos += 1;
+		}
+
+		emu.tick();
+		self.wait = emu.wait_for_keypress;
+	}
+
+	/// Restart the emulator and play the movie up to the frame, so the edited input takes effect
+	/// Breakpoints and watchpoints are ignored and the heat is kept as is on the way
+	pub fn seek(&mut self, emu: &mut Emu, frame: usize) -> Result<(), MovieError> {
+		let debugger = std::mem::take(&mut emu.debugger);
+		let heat = emu.heat;
+
+		let result = self.movie.setup(emu);
+		if result.is_ok() {
+			self.ended = false;
+			self.pos = 0;
+			self.wait = emu.wait_for_keypress;
+
+			while self.pos < frame.min(self.movie.frames.len()) {
+				self.tick(emu);
+			}
+		}
+
+		emu.debugger = debugger;
+		emu.heat = heat;
+		result
+	}
+	/// Press or release the key on the frame and simulate the movie up to the current frame again
+	pub fn toggle_key(&mut self, emu: &mut Emu, frame: usize, key: u8) -> Result<(), MovieError> {
+		let was_held = frame
+			.checked_sub(1)
+			.is_some_and(|prev| self.movie.frames[prev].is_key_held(key));
+
+		let edited = &mut self.movie.frames[frame];
+		edited.keys ^= 1 << key;
+
+		// Newly held key ends the `Fx0A` wait as a press on the keyboard does
+		if edited.is_key_held(key) && !was_held && edited.press.is_none() {
+			edited.press = Some(key);
+		} else if !edited.is_key_held(key) && edited.press == Some(key) {
+			edited.press = None;
+		}
+
+		if frame < self.pos {
+			self.seek(emu, self.pos)?;
+		}
+		Ok(())
+	}
+
+	fn record_frame(&mut self, emu: &Emu) {
+		let keys = (0..Emu::KEYS_COUNT)
+			.filter(|key| emu.pressed_keys[*key])
+			.fold(0, |mask, key| mask | 1 << key);
+
+		// The wait can only be ended by a key press
+		let press = match (self.wait, emu.wait_for_keypress) {
+			(Some(x), None) => Some(emu.regs[x]),
+			_ => None,
+		};
+
+		self.movie.frames.push(MovieFrame {
+			keys,
+			press,
+			speed: emu.speed,
+		});
+	}
+	fn apply_frame(emu: &mut Emu, frame: &MovieFrame) {
+		for key in 0..Emu::KEYS_COUNT as u8 {
+			emu.set_pressed_key(key, frame.is_key_held(key), false);
+		}
+		if let Some(key) = frame.press {
+			emu.set_pressed_key(key, frame.is_key_held(key), true);
+		}
+		emu.set_speed(frame.speed);
+	}
+
+	/// Save the recorded movie into the tape file, if any
+	#[cfg(not(target_arch = "wasm32"))]
+	pub fn save(&self) {
+		let Some(path) = &self.path else {
+			return;
+		};
+		if self.mode != TapeMode::Record {
+			return;
+		}
+
+		match std::fs::write(path, self.movie.save()) {
+			Ok(()) => println!(
+				"Movie with {} frames is saved to {}",
+				self.movie.frames.len(),
+				path.display()
+			),
+			Err(e) => eprintln!("ERROR: failed to save movie {}: {e}", path.display()),
+		}
+	}
+}
+
+/// Run a single frame of the paused emulator, through the tape if there is one
+pub fn step_frame(emu: &mut Emu, tape: Option<&mut MovieTape>) {
+	match tape {
+		Some(tape) => emu.step_frame_with(|emu| tape.tick(emu)),
+		None => emu.step_frame(),
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::emu::asm;
+
+	const SOURCE: &str = "
+		: main
+			loop
+				v0 := key
+				v1 := random 0x3F
+				i := hex v0
+				sprite v1 v2 5
+				v2 += 3
+				v3 := 5
+				if v3 key then v4 += 1
+			again
+	";
+	const FRAMES: usize = 300;
+
+	#[test]
+	fn record_and_play() {
+		let program = asm::assemble(SOURCE).unwrap();
+
+		let mut emu = Box::new(Emu::default());
+		emu.set_seed(7);
+		emu.load(&program);
+
+		let mut tape = MovieTape::record(&emu, None);
+		for frame in 0..FRAMES {
+			// Hold keys in turns and tap others between two ticks, like the keyboard does
+			let key = (frame / 10 % Emu::KEYS_COUNT) as u8;
+			match frame % 10 {
+				0 => emu.set_pressed_key(key, true, true),
+				3 => emu.set_pressed_key(key, false, false),
+				6 => {
+					emu.set_pressed_key(key ^ 1, true, true);
+					emu.set_pressed_key(key ^ 1, false, false);
+				}
+				_ => (),
+			}
+			if frame == 100 {
+				emu.set_speed(2.5);
+			}
+
+			tape.tick(&mut emu);
+		}
+		assert!(!tape.ended);
+		assert!(tape.movie.frames.iter().any(|frame| frame.press.is_some()));
+
+		let movie = Movie::load(&tape.movie.save()).unwrap();
+		assert_eq!(movie, tape.movie);
+
+		let mut replay = Box::new(Emu::default());
+		replay.load(&program);
+		let mut tape = MovieTape::play(movie, &mut replay).unwrap();
+		for _ in 0..FRAMES {
+			tape.tick(&mut replay);
+		}
+		assert!(!tape.ended);
+		assert_eq!(replay.screen_hash(), emu.screen_hash());
+		assert_eq!(*replay.regs, *emu.regs);
+		assert_eq!(replay.pc, emu.pc);
+		assert_eq!(replay.speed, 2.5);
+
+		tape.tick(&mut replay);
+		assert!(tape.ended);
+
+		let mut other = Box::new(Emu::default());
+		other.load(&[0x12, 0x00]);
+		assert!(matches!(
+			MovieTape::play(tape.movie, &mut other),
+			Err(MovieError::RomMismatch)
+		));
+	}
+
+	#[test]
+	fn edit_and_seek() {
+		let program = asm::assemble(SOURCE).unwrap();
+
+		let mut emu = Box::new(Emu::default());
+		emu.load(&program);
+
+		// Record the program waiting for a key which is never pressed
+		let mut tape = MovieTape::record(&emu, None);
+		for _ in 0..FRAMES {
+			tape.tick(&mut emu);
+		}
+		assert_eq!(emu.regs[2], 0);
+
+		// Pressing a key in the past ends the wait
+		tape.toggle_key(&mut emu, 10, 0xA).unwrap();
+		assert_eq!(tape.movie.frames[10].press, Some(0xA));
+		assert_eq!(tape.pos(), FRAMES);
+		assert_eq!(emu.frame as usize, FRAMES);
+		assert_eq!(emu.regs[0], 0xA);
+		assert_eq!(emu.regs[2], 3);
+
+		// Recording goes on after the played frames
+		tape.seek(&mut emu, 20).unwrap();
+		assert!(tape.is_playing());
+		for _ in 20..FRAMES + 5 {
+			tape.tick(&mut emu);
+		}
+		assert!(!tape.ended);
+		assert_eq!(tape.movie.frames.len(), FRAMES + 5);
+
+		tape.toggle_key(&mut emu, 10, 0xA).unwrap();
+		assert_eq!(tape.movie.frames[10].press, None);
+		assert_eq!(emu.regs[2], 0);
+	}
+}
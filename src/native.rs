@@ -10,6 +10,9 @@ pub use std::time::Instant as NativeInstant;
 #[cfg(target_arch = "wasm32")]
 pub use wasm::Instant as NativeInstant;
 
+#[cfg(target_arch = "wasm32")]
+pub use wasm::gamepad_buttons;
+
 #[allow(unreachable_code)]
 pub fn open_url(url: &str) -> Result<(), &'static str> {
 	println!("Opening url {url:?}...");
@@ -69,3 +72,30 @@ pub fn now_millis() -> i32 {
 		wasm::date_now()
 	}
 }
+
+/// Item of the browser local storage
+#[cfg(target_arch = "wasm32")]
+pub fn storage_get(key: &str) -> Option<String> {
+	unsafe {
+		let len = wasm::storage_len(key.as_ptr() as _, key.len());
+		if len < 0 {
+			return None;
+		}
+
+		let mut buf = vec![0; len as usize];
+		wasm::storage_read(key.as_ptr() as _, key.len(), buf.as_mut_ptr(), buf.len());
+		String::from_utf8(buf).ok()
+	}
+}
+/// Set the item of the browser local storage
+#[cfg(target_arch = "wasm32")]
+pub fn storage_set(key: &str, value: &str) {
+	unsafe {
+		wasm::storage_write(
+			key.as_ptr() as _,
+			key.len(),
+			value.as_ptr() as _,
+			value.len(),
+		);
+	}
+}
@@ -4,6 +4,13 @@ unsafe extern "C" {
 	pub fn performance_now() -> i32;
 	pub fn date_now() -> i32;
 	pub fn window_open(url: *const i8, len: usize);
+	/// Mask of the buttons pressed on any of the gamepads in the standard layout
+	pub fn gamepad_buttons() -> u32;
+	/// Size of the local storage item in bytes, `-1` if there is no such item
+	pub fn storage_len(key: *const i8, key_len: usize) -> i32;
+	/// Copy the local storage item into the buffer
+	pub fn storage_read(key: *const i8, key_len: usize, buf: *mut u8, buf_len: usize);
+	pub fn storage_write(key: *const i8, key_len: usize, value: *const i8, value_len: usize);
 }
 
 pub struct Instant {
@@ -0,0 +1,31 @@
+/// Colors of the game display
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum Palette {
+	#[default]
+	Green,
+	Amber,
+	Gray,
+	Blue,
+}
+impl Palette {
+	pub fn from_name(name: &str) -> Option<Self> {
+		match name {
+			"green" => Some(Self::Green),
+			"amber" => Some(Self::Amber),
+			"gray" | "grey" => Some(Self::Gray),
+			"blue" => Some(Self::Blue),
+			_ => None,
+		}
+	}
+
+	/// Colors of pixels lit on the first, the second and both XO-CHIP planes
+	pub fn colors(self) -> [[u8; 3]; 3] {
+		let colors = match self {
+			Self::Green => [[0.7, 0.8, 0.7], [0.35, 0.5, 0.4], [0.9, 1.0, 0.85]],
+			Self::Amber => [[1.0, 0.7, 0.25], [0.55, 0.35, 0.1], [1.0, 0.9, 0.6]],
+			Self::Gray => [[0.8, 0.8, 0.8], [0.45, 0.45, 0.45], [1.0, 1.0, 1.0]],
+			Self::Blue => [[0.55, 0.75, 1.0], [0.25, 0.35, 0.6], [0.85, 0.95, 1.0]],
+		};
+		colors.map(|color| color.map(|channel: f32| (255.0 * channel) as u8))
+	}
+}
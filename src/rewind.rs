@@ -0,0 +1,67 @@
+use std::collections::VecDeque;
+
+use crate::emu::{Emu, SnapshotReader, SnapshotWriter};
+
+/// Bounded history of compressed emulator snapshots
+#[derive(Default)]
+pub struct Rewind {
+	snapshots: VecDeque<Vec<u8>>,
+	/// Emulator frame of the last taken snapshot
+	last_frame: Option<u32>,
+}
+impl Rewind {
+	/// Take a snapshot every N emulator frames
+	pub const INTERVAL: u32 = 2;
+	/// Max number of stored snapshots, 10 seconds of history
+	pub const CAPACITY: usize = 300;
+
+	/// Take a snapshot if enough frames passed since the last one
+	pub fn record(&mut self, emu: &Emu) {
+		if let Some(last) = self.last_frame
+			&& emu.frame.wrapping_sub(last) < Self::INTERVAL
+		{
+			return;
+		}
+
+		let mut w = SnapshotWriter::default();
+		emu.write_snapshot(&mut w);
+
+		if self.snapshots.len() == Self::CAPACITY {
+			self.snapshots.pop_front();
+		}
+		self.snapshots
+			.push_back(lz4_flex::compress_prepend_size(&w.bytes));
+		self.last_frame = Some(emu.frame);
+	}
+
+	/// Restore the latest snapshot and drop it from the history
+	/// Returns `false` if there is nothing to rewind
+	pub fn step_back(&mut self, emu: &mut Emu) -> bool {
+		let Some(bytes) = self.snapshots.pop_back() else {
+			return false;
+		};
+		let Ok(body) = lz4_flex::decompress_size_prepended(&bytes) else {
+			return false;
+		};
+
+		// Board heat is not rewound, otherwise overheating could be undone
+		let heat = emu.heat;
+		let mut restored = Box::new(emu.clone());
+		if restored
+			.read_snapshot(&mut SnapshotReader::new(&body))
+			.is_none()
+		{
+			return false;
+		}
+		*emu = *restored;
+		emu.heat = heat;
+
+		self.last_frame = Some(emu.frame);
+		true
+	}
+
+	pub fn clear(&mut self) {
+		self.snapshots.clear();
+		self.last_frame = None;
+	}
+}
@@ -0,0 +1,109 @@
+use std::{fmt, io, path::PathBuf};
+
+use crate::emu::{Emu, Mode, asm::AsmError};
+
+/// ROM loading error
+#[derive(Debug)]
+pub enum RomError {
+	NotFound,
+	Permission,
+	Empty,
+	/// ROM doesn't fit into the memory of the selected mode
+	TooLarge {
+		size: usize,
+		max: usize,
+	},
+	/// ROM has an odd number of bytes, so it's probably broken
+	/// It is only a warning, the ROM is loaded anyway
+	OddLength(usize),
+	/// Octo source failed to assemble
+	Assembly(AsmError),
+	Io(io::Error),
+}
+impl RomError {
+	pub fn is_warning(&self) -> bool {
+		matches!(self, Self::OddLength(_))
+	}
+	/// Print the error to stderr
+	pub fn print(&self, path: Option<&PathBuf>) {
+		let kind = if self.is_warning() {
+			"WARNING"
+		} else {
+			"ERROR"
+		};
+		match path {
+			Some(path) => eprintln!("{kind}: {}: {self}", path.display()),
+			None => eprintln!("{kind}: {self}"),
+		}
+	}
+}
+impl From<io::Error> for RomError {
+	fn from(e: io::Error) -> Self {
+		match e.kind() {
+			io::ErrorKind::NotFound => Self::NotFound,
+			io::ErrorKind::PermissionDenied => Self::Permission,
+			_ => Self::Io(e),
+		}
+	}
+}
+impl fmt::Display for RomError {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		match self {
+			Self::NotFound => write!(f, "ROM file not found"),
+			Self::Permission => write!(f, "No permission to read the ROM file"),
+			Self::Empty => write!(f, "ROM file is empty"),
+			Self::TooLarge { size, max } => {
+				write!(f, "ROM is too large: {size} bytes, max is {max}")
+			}
+			Self::OddLength(size) => write!(f, "ROM has odd length of {size} bytes"),
+			Self::Assembly(e) => write!(f, "Failed to assemble: {e}"),
+			Self::Io(e) => write!(f, "{e}"),
+		}
+	}
+}
+
+/// Read the ROM file
+/// Octo sources (`.8o`) are assembled
+#[cfg(not(target_arch = "wasm32"))]
+pub fn read_rom(path: impl AsRef<std::path::Path>) -> Result<Vec<u8>, RomError> {
+	let path = path.as_ref();
+
+	let bytes = if path.extension().is_some_and(|ext| ext == "8o") {
+		let source = std::fs::read_to_string(path)?;
+		crate::emu::asm::assemble(&source).map_err(RomError::Assembly)?
+	} else {
+		std::fs::read(path)?
+	};
+	if bytes.is_empty() {
+		return Err(RomError::Empty);
+	}
+
+	Ok(bytes)
+}
+
+/// Path of the file next to the ROM with the suffix appended to its name,
+/// e.g. `game.ch8.keymap` for the `.keymap` suffix
+#[cfg(not(target_arch = "wasm32"))]
+pub fn rom_sibling(rom_path: &std::path::Path, suffix: &str) -> Option<PathBuf> {
+	let mut name = rom_path.file_name()?.to_os_string();
+	name.push(suffix);
+	Some(rom_path.with_file_name(name))
+}
+
+/// Check whether the ROM can be loaded in the specified mode
+/// Returns [`RomError::OddLength`] warning if the ROM can be loaded, but it looks suspicious
+pub fn check_rom(rom: &[u8], mode: Mode) -> Result<Option<RomError>, RomError> {
+	let max = Emu::max_program_size(mode);
+	if rom.len() > max {
+		return Err(RomError::TooLarge {
+			size: rom.len(),
+			max,
+		});
+	}
+
+	if !rom.len().is_multiple_of(2) {
+		return Ok(Some(RomError::OddLength(rom.len())));
+	}
+
+	Ok(None)
+}
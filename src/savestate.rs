@@ -0,0 +1,141 @@
+use std::{fmt, path::PathBuf};
+
+use crate::{
+	emu::{SnapshotReader, SnapshotWriter},
+	state::State,
+};
+
+/// Save state error
+#[derive(Debug)]
+pub enum SaveStateError {
+	/// Not a save state file
+	BadMagic,
+	/// Save state was made by an incompatible version
+	Version(u16),
+	/// Save state was made with a different ROM
+	RomMismatch,
+	/// Save state data is truncated or damaged
+	Corrupted,
+	/// Slot doesn't contain a save state
+	Empty,
+	Io(std::io::Error),
+}
+impl fmt::Display for SaveStateError {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		match self {
+			Self::BadMagic => write!(f, "Not a save state"),
+			Self::Version(v) => write!(f, "Unsupported save state version {v}"),
+			Self::RomMismatch => write!(f, "Save state is for another ROM"),
+			Self::Corrupted => write!(f, "Save state is corrupted"),
+			Self::Empty => write!(f, "Slot is empty"),
+			Self::Io(e) => write!(f, "{e}"),
+		}
+	}
+}
+
+/// Snapshot of the whole machine: emulator and the board
+///
+/// Format:
+/// - magic bytes `CMXS`
+/// - format version (u16)
+/// - hash of the ROM the snapshot was made with (u64)
+/// - LZ4 compressed body with the board and emulator state
+pub struct SaveState;
+impl SaveState {
+	const MAGIC: &[u8; 4] = b"CMXS";
+	pub const VERSION: u16 = 3;
+
+	pub fn save(state: &State) -> Vec<u8> {
+		let mut body = SnapshotWriter::default();
+		body.bool(state.board.power);
+		body.f32(state.valve.angle.borrow().value);
+		state.emu.write_snapshot(&mut body);
+
+		let mut w = SnapshotWriter::default();
+		w.slice(Self::MAGIC);
+		w.u16(Self::VERSION);
+		w.u64(state.emu.program_hash());
+		w.slice(&lz4_flex::compress_prepend_size(&body.bytes));
+		w.bytes
+	}
+	/// Restore the machine from the save state
+	/// Nothing is changed if the save state is invalid
+	pub fn load(state: &mut State, bytes: &[u8]) -> Result<(), SaveStateError> {
+		let mut r = SnapshotReader::new(bytes);
+		if r.slice(Self::MAGIC.len()) != Some(Self::MAGIC) {
+			return Err(SaveStateError::BadMagic);
+		}
+
+		let version = r.u16().ok_or(SaveStateError::Corrupted)?;
+		if version != Self::VERSION {
+			return Err(SaveStateError::Version(version));
+		}
+		if r.u64() != Some(state.emu.program_hash()) {
+			return Err(SaveStateError::RomMismatch);
+		}
+
+		let body =
+			lz4_flex::decompress_size_prepended(r.rest()).map_err(|_| SaveStateError::Corrupted)?;
+
+		// Read into a copy, so a broken body doesn't leave the machine half restored
+		let mut r = SnapshotReader::new(&body);
+		let power = r.bool().ok_or(SaveStateError::Corrupted)?;
+		let angle = r.f32().ok_or(SaveStateError::Corrupted)?;
+
+		let mut emu = Box::new(state.emu.clone());
+		emu.read_snapshot(&mut r).ok_or(SaveStateError::Corrupted)?;
+
+		state.emu = *emu;
+		state.board.power = power;
+		state.valve.angle.borrow_mut().value = angle;
+		Ok(())
+	}
+}
+
+/// Numbered save state slots
+/// On native builds slots are also stored next to the ROM file
+pub struct SaveSlots {
+	slots: [Option<Vec<u8>>; Self::COUNT],
+	rom_path: Option<PathBuf>,
+}
+impl SaveSlots {
+	pub const COUNT: usize = 4;
+
+	pub fn new(rom_path: Option<PathBuf>) -> Self {
+		Self {
+			slots: Default::default(),
+			rom_path,
+		}
+	}
+
+	/// Path of the slot file, e.g. `game.ch8.state1`
+	#[cfg(not(target_arch = "wasm32"))]
+	fn slot_path(&self, slot: usize) -> Option<PathBuf> {
+		crate::rom::rom_sibling(self.rom_path.as_ref()?, &format!(".state{}", slot + 1))
+	}
+
+	pub fn save(&mut self, slot: usize, state: &State) -> Result<(), SaveStateError> {
+		let bytes = SaveState::save(state);
+
+		#[cfg(not(target_arch = "wasm32"))]
+		if let Some(path) = self.slot_path(slot) {
+			std::fs::write(path, &bytes).map_err(SaveStateError::Io)?;
+		}
+
+		self.slots[slot] = Some(bytes);
+		Ok(())
+	}
+	pub fn load(&mut self, slot: usize, state: &mut State) -> Result<(), SaveStateError> {
+		#[cfg(not(target_arch = "wasm32"))]
+		if self.slots[slot].is_none()
+			&& let Some(path) = self.slot_path(slot)
+		{
+			self.slots[slot] = std::fs::read(path).ok();
+		}
+
+		match &self.slots[slot] {
+			Some(bytes) => SaveState::load(state, bytes),
+			None => Err(SaveStateError::Empty),
+		}
+	}
+}
@@ -5,11 +5,15 @@ mod instuction_leds;
 mod key;
 mod keyboard;
 mod links;
+mod listing;
 mod movie_display;
+mod piano_roll;
 mod registers_display;
 mod reset_button;
+mod settings_panel;
 mod state_leds;
 mod switch;
+mod text_panel;
 mod timers;
 mod titles_display;
 mod valve;
@@ -20,10 +24,13 @@ use game_display::GameDisplay;
 use instuction_leds::InstuctionLeds;
 use keyboard::Keyboard;
 use links::Links;
+use listing::Listing;
 use miniquad::{KeyCode, window};
 use movie_display::MovieDisplay;
+use piano_roll::PianoRoll;
 use registers_display::RegistersDisplay;
 use reset_button::ResetButton;
+use settings_panel::{SettingsAction, SettingsPanel};
 use state_leds::StateLeds;
 use switch::Switch;
 use timers::Timers;
@@ -31,10 +38,16 @@ use titles_display::TitlesDisplay;
 use valve::Valve;
 
 use crate::{
-	audio::Sound,
+	audio::{BuzzHandle, BuzzParams, Sound},
+	games::GameInfo,
 	input::InputConsume,
+	keymap::Keymap,
 	math::{Color, Rect},
+	movie::{self, MovieTape, TapeMode},
 	painter::{BlendMode, CanvasId, Icon, IconKind, Merge, Sprite, Text},
+	rewind::Rewind,
+	rom::check_rom,
+	savestate::SaveSlots,
 	state::{BoardSide, State},
 	util::{Anim, AnimRef, AnimWait, Easing, Keyframe, Timeline, TweenPlay},
 };
@@ -50,6 +63,14 @@ enum BoardAnim {
 	Falling,
 }
 
+/// Text panel over the board, only one of them is open at a time
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Panel {
+	Listing,
+	PianoRoll,
+	Settings,
+}
+
 /// Board timeline action
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 enum Action {
@@ -58,27 +79,19 @@ enum Action {
 	Reset,
 }
 
-fn new_buzz_sound(_ctx: &mut AppContext) -> Sound {
-	#[cfg(not(target_arch = "wasm32"))]
-	{
-		use rodio::source::Source;
-
-		_ctx.audio.new_sound(
-			rodio::source::TriangleWave::new(200.0)
-				.high_pass(500)
-				.amplify(0.2),
-		)
-	}
-
-	#[cfg(target_arch = "wasm32")]
-	crate::audio::Sound
-}
-
 /// Scene
 pub struct Scene {
 	front_board: FrontBoard,
 	back_board: BackBoard,
 	picker: CartridgePicker,
+	listing: Listing,
+	piano_roll: PianoRoll,
+	settings_panel: SettingsPanel,
+	open_panel: Option<Panel>,
+	saves: SaveSlots,
+	rewind: Rewind,
+	/// Emulator ticks are this many times longer than usual
+	slow_motion: u32,
 
 	/// Board power state before flipping
 	was_power: bool,
@@ -92,6 +105,7 @@ pub struct Scene {
 	explosion_anim: AnimRef,
 
 	buzz_sound: Sound,
+	buzz_handle: BuzzHandle,
 	whistle_sound: Sound,
 
 	/// Layer with the "normal" blend mode
@@ -100,8 +114,9 @@ pub struct Scene {
 	add_layer: CanvasId,
 }
 impl Scene {
-	pub fn new(ctx: &mut AppContext, state: &State) -> Self {
-		let front_board = FrontBoard::new(ctx);
+	pub fn new(ctx: &mut AppContext, state: &State, user_games: Vec<GameInfo>) -> Self {
+		let mut front_board = FrontBoard::new(ctx);
+		front_board.keyboard.keymap = Keymap::load(state.rom_path.as_deref());
 
 		let flip_anim = Anim::new(8, 0..ctx.assets.board_flip.frames.x).into_ref();
 		let fall_anim = Anim::new(8, 0..ctx.assets.board_fall.frames.x).into_ref();
@@ -135,6 +150,8 @@ impl Scene {
 			Keyframe::action(Action::SetAnim(BoardAnim::Front)),
 		]);
 
+		let (buzz_sound, buzz_handle) = ctx.audio.new_buzz_sound();
+
 		let whistle_sound = ctx
 			.audio
 			.new_sound_from_vorbis(ctx.assets.whistle_sound, true);
@@ -144,7 +161,14 @@ impl Scene {
 		Self {
 			front_board,
 			back_board: BackBoard::new(ctx),
-			picker: CartridgePicker::default(),
+			picker: CartridgePicker::new(user_games),
+			listing: Listing::default(),
+			piano_roll: PianoRoll,
+			settings_panel: SettingsPanel,
+			open_panel: None,
+			saves: SaveSlots::new(state.rom_path.clone()),
+			rewind: Rewind::default(),
+			slow_motion: 1,
 
 			was_power: state.board.power,
 			cur_board_anim: match state.board.side {
@@ -159,7 +183,8 @@ impl Scene {
 			fall_anim,
 			explosion_anim,
 
-			buzz_sound: new_buzz_sound(ctx),
+			buzz_sound,
+			buzz_handle,
 			whistle_sound,
 
 			normal_layer: ctx.painter.context.new_canvas(
@@ -194,11 +219,15 @@ impl Scene {
 	}
 
 	pub fn update(&mut self, ctx: &mut AppContext, state: &mut State) {
+		self.update_picker(ctx, state);
 		self.update_anims(ctx);
 		self.update_timelines(ctx, state);
 		self.update_boards(ctx, state);
-		self.update_emu(state);
+		self.update_emu(ctx, state);
 		self.update_heat(state);
+		self.update_saves(ctx, state);
+		self.update_slow_motion(ctx);
+		self.update_panels(ctx, state);
 
 		if cfg!(debug_assertions) && ctx.input.key_just_pressed(KeyCode::Enter) {
 			self.explode();
@@ -210,6 +239,105 @@ impl Scene {
 			self.flip_timeline.playing || self.explode_timeline.playing,
 		);
 	}
+	/// Toggle the listing with `F5`, the piano roll with `F9` and the settings with `F12`
+	fn update_panels(&mut self, ctx: &mut AppContext, state: &mut State) {
+		let keys = [
+			(KeyCode::F5, Panel::Listing),
+			(KeyCode::F9, Panel::PianoRoll),
+			(KeyCode::F12, Panel::Settings),
+		];
+		for (key, panel) in keys {
+			if ctx.input.key_just_pressed(key) {
+				let panel = (self.open_panel != Some(panel)).then_some(panel);
+				self.set_panel(ctx, state, panel);
+			}
+		}
+
+		// The roll is closed with the tape, e.g. when it is rewound
+		if self.open_panel == Some(Panel::PianoRoll) && state.tape.is_none() {
+			self.open_panel = None;
+		}
+
+		match self.open_panel {
+			Some(Panel::Listing) => self.listing.update(ctx, &mut state.emu),
+			Some(Panel::PianoRoll) => self.piano_roll.update(ctx, state),
+			Some(Panel::Settings) => match self.settings_panel.update(ctx, state) {
+				Some(SettingsAction::FlipBoard) => self.flip(ctx, state),
+				Some(SettingsAction::OpenPicker) if state.board.side == BoardSide::Front => {
+					self.picker.is_open = true;
+				}
+				Some(SettingsAction::OpenPicker) => {
+					ctx.tooltip
+						.set_error(b"Flip the board to insert a cartridge");
+				}
+				None => (),
+			},
+			None => (),
+		}
+	}
+	/// Open the panel in place of the open one, or close the open one with `None`
+	/// Opening the piano roll pauses the emulator and starts recording a movie if there is none
+	fn set_panel(&mut self, ctx: &mut AppContext, state: &mut State, panel: Option<Panel>) {
+		if panel == self.open_panel {
+			return;
+		}
+
+		#[cfg(not(target_arch = "wasm32"))]
+		if self.open_panel == Some(Panel::PianoRoll)
+			&& let Some(tape) = &state.tape
+		{
+			tape.save();
+		}
+		if panel == Some(Panel::PianoRoll) {
+			self.start_tas(ctx, state);
+		}
+
+		self.open_panel = panel;
+	}
+	/// Open the cartridge picker with `Tab` and insert the picked cartridge
+	fn update_picker(&mut self, ctx: &mut AppContext, state: &mut State) {
+		ctx.input.consume(InputConsume::PICKER, false);
+
+		let was_open = self.picker.is_open;
+		if was_open {
+			if let Some(info) = self.picker.update(ctx) {
+				self.insert_cartridge(ctx, state, &info);
+			}
+		} else if state.board.side == BoardSide::Front && ctx.input.key_just_pressed(KeyCode::Tab) {
+			self.picker.is_open = true;
+		}
+
+		// Keep the board input blocked on the closing frame too, so keys used in the picker
+		// don't leak onto the board
+		ctx.input
+			.consume(InputConsume::PICKER, was_open || self.picker.is_open);
+	}
+	/// Load the game into the emulator and power-cycle the board
+	fn insert_cartridge(&mut self, ctx: &mut AppContext, state: &mut State, info: &GameInfo) {
+		match check_rom(&info.bytes, info.mode) {
+			Ok(None) => (),
+			Ok(Some(warning)) => ctx.tooltip.set_error(warning.to_string().as_bytes()),
+			Err(e) => {
+				ctx.tooltip.set_error(e.to_string().as_bytes());
+				return;
+			}
+		}
+
+		state.emu.mode = info.mode;
+		state.emu.quirks = info.quirks.quirks();
+		state.emu.load(&info.bytes);
+		state.reset();
+		state.rom_path = info.path.clone();
+		state.board.switch_power(ctx, true);
+
+		self.saves = SaveSlots::new(state.rom_path.clone());
+		self.rewind.clear();
+		// The movie is only valid for the ROM it was started with
+		if let Some(tape) = &mut state.tape {
+			tape.ended = true;
+		}
+		self.front_board.keyboard.keymap = Keymap::load(state.rom_path.as_deref());
+	}
 	fn update_boards(&mut self, ctx: &mut AppContext, state: &mut State) {
 		let back_factor = match self.cur_board_anim {
 			BoardAnim::Front => 0.0,
@@ -221,18 +349,123 @@ impl Scene {
 		self.front_board.update(ctx, state, 1.0 - back_factor);
 		self.back_board.update(ctx, back_factor);
 	}
-	fn update_emu(&mut self, state: &mut State) {
+	fn update_emu(&mut self, ctx: &mut AppContext, state: &mut State) {
+		// Rewind the emulator while the key is held
+		state.board.rewinding = state.board.power && ctx.input.key_is_pressed(KeyCode::Backspace);
+
 		// Update emulator
-		if state.board.power {
-			state.emu.update();
+		if state.board.rewinding {
+			self.rewind.step_back(&mut state.emu);
+		} else if state.board.power {
+			let was_hit = state.emu.debugger.hit;
+
+			self.update_debugger(ctx, state);
+			if !state.emu.debugger.paused {
+				let delta = ctx.time.delta / self.slow_motion;
+				match &mut state.tape {
+					Some(tape) => state.emu.update_with(delta, |emu| tape.tick(emu)),
+					None => state.emu.update(delta),
+				}
+			}
+			self.rewind.record(&state.emu);
+
+			if let Some(hit) = state.emu.debugger.hit
+				&& was_hit != Some(hit)
+			{
+				ctx.tooltip.set_error(hit.to_string().as_bytes());
+			}
 		}
 
+		self.update_tape(ctx, state);
+
 		// Update buzz sound
-		let play = state.emu.sound_timer > 0 && state.board.power;
+		let play = state.emu.sound_timer > 0 && state.board.power && !state.board.rewinding;
 		self.buzz_sound.set_playing(play);
+		self.buzz_handle.set(BuzzParams {
+			pattern: state.emu.audio_pattern,
+			rate: state.emu.audio_pattern_rate(),
+		});
+	}
+	/// Detach the tape once it has ended and save the recorded movie
+	fn update_tape(&mut self, ctx: &mut AppContext, state: &mut State) {
+		let Some(tape) = state.tape.take_if(|tape| tape.ended) else {
+			return;
+		};
+
+		match tape.mode {
+			TapeMode::Record => {
+				#[cfg(not(target_arch = "wasm32"))]
+				tape.save();
+				ctx.tooltip.set_error(b"Recording stopped");
+			}
+			TapeMode::Play => ctx.tooltip.set(b"Movie ended"),
+		}
+	}
+	/// Pause or resume with `F6`, while paused step a single instruction with `F7`
+	/// and a single frame with `F8`
+	fn update_debugger(&mut self, ctx: &AppContext, state: &mut State) {
+		if ctx.input.key_just_pressed(KeyCode::F6) {
+			state.emu.debugger.toggle_pause();
+			return;
+		}
+		if !state.emu.debugger.paused {
+			return;
+		}
+
+		if ctx.input.key_just_pressed(KeyCode::F7) {
+			state.emu.step_ins();
+		} else if ctx.input.key_just_pressed(KeyCode::F8) {
+			movie::step_frame(&mut state.emu, state.tape.as_mut());
+		}
+	}
+	/// Cycle the slow motion with `F10`
+	fn update_slow_motion(&mut self, ctx: &mut AppContext) {
+		const SLOW_MOTION_MAX: u32 = 8;
+
+		if ctx.input.key_just_pressed(KeyCode::F10) {
+			self.slow_motion = match self.slow_motion {
+				SLOW_MOTION_MAX => 1,
+				n => n * 2,
+			};
+			ctx.tooltip
+				.set(format!("Slow motion 1/{}", self.slow_motion).as_bytes());
+		}
+	}
+	fn start_tas(&mut self, ctx: &mut AppContext, state: &mut State) {
+		match &mut state.tape {
+			// Continue recording from the end of the played movie
+			Some(tape) if !tape.ended => tape.mode = TapeMode::Record,
+			_ => {
+				// Movies always start from the power-on
+				state.emu.setup();
+				self.rewind.clear();
+
+				#[cfg(not(target_arch = "wasm32"))]
+				let path = state
+					.rom_path
+					.as_ref()
+					.and_then(|path| crate::rom::rom_sibling(path, ".cmx"));
+				#[cfg(target_arch = "wasm32")]
+				let path = None;
+
+				state.tape = Some(MovieTape::record(&state.emu, path));
+			}
+		}
+
+		state.board.switch_power(ctx, true);
+		state.emu.debugger.pause();
 	}
 	fn update_heat(&mut self, state: &mut State) {
-		state.emu.cool_down(1.0);
+		// Heat is frozen while rewinding, so a rewind can't blow up the board
+		if state.board.rewinding {
+			return;
+		}
+
+		if state.overheat {
+			state.emu.cool_down(1.0);
+		} else {
+			state.emu.heat = 0.0;
+		}
 
 		if state.emu.is_critical_heat() {
 			self.explode();
@@ -243,6 +476,35 @@ impl Scene {
 		self.whistle_sound.set_volume(volume);
 	}
 
+	/// Save with `Shift+F1..F4` and load with `F1..F4`
+	fn update_saves(&mut self, ctx: &mut AppContext, state: &mut State) {
+		const KEYS: [KeyCode; SaveSlots::COUNT] =
+			[KeyCode::F1, KeyCode::F2, KeyCode::F3, KeyCode::F4];
+
+		let Some(slot) = KEYS.iter().position(|key| ctx.input.key_just_pressed(*key)) else {
+			return;
+		};
+		let shift = ctx.input.key_is_pressed(KeyCode::LeftShift)
+			|| ctx.input.key_is_pressed(KeyCode::RightShift);
+
+		let (result, action) = if shift {
+			(self.saves.save(slot, state), "Saved")
+		} else {
+			(self.saves.load(slot, state), "Loaded")
+		};
+
+		if result.is_ok() && !shift {
+			self.rewind.clear();
+		}
+
+		match result {
+			Ok(()) => ctx
+				.tooltip
+				.set(format!("{action} slot {}", slot + 1).as_bytes()),
+			Err(e) => ctx.tooltip.set_error(e.to_string().as_bytes()),
+		}
+	}
+
 	fn update_anims(&mut self, ctx: &AppContext) {
 		self.flip_anim.borrow_mut().update(&ctx.time);
 		self.fall_anim.borrow_mut().update(&ctx.time);
@@ -276,8 +538,18 @@ impl Scene {
 					state.board.switch_power(ctx, false);
 				}
 			}
-			Action::SetAnim(anim) => self.cur_board_anim = anim,
-			Action::Reset => state.reset(),
+			Action::SetAnim(anim) => {
+				self.cur_board_anim = anim;
+				match anim {
+					BoardAnim::Front => state.board.side = BoardSide::Front,
+					BoardAnim::Back => state.board.side = BoardSide::Back,
+					BoardAnim::Flipping | BoardAnim::Falling => (),
+				}
+			}
+			Action::Reset => {
+				state.reset();
+				self.rewind.clear();
+			}
 		}
 	}
 
@@ -357,7 +629,15 @@ impl Scene {
 		self.draw_flip_trigger(ctx, state, canvas);
 		self.draw_tooltip(ctx, canvas);
 
-		// self.picker.draw(ctx, canvas);
+		match self.open_panel {
+			Some(Panel::Listing) => self.listing.draw(ctx, &state.emu, canvas),
+			Some(Panel::PianoRoll) => self.piano_roll.draw(ctx, state, canvas),
+			Some(Panel::Settings) => self.settings_panel.draw(ctx, state, canvas),
+			None => (),
+		}
+		if self.picker.is_open {
+			self.picker.draw(ctx, canvas);
+		}
 	}
 
 	fn draw_tooltip(&self, ctx: &mut AppContext, canvas: CanvasId) {
@@ -459,7 +739,7 @@ impl FrontBoard {
 			valve: Valve::new(ctx),
 			ins_leds: InstuctionLeds::default(),
 			state_leds: StateLeds::default(),
-			keyboard: Keyboard,
+			keyboard: Keyboard::default(),
 			switch: Switch,
 			timers: Timers,
 			reset_button: ResetButton,
@@ -1,31 +1,36 @@
 use std::{cmp::Ordering, f32};
 
+use miniquad::KeyCode;
+
 use crate::{
 	app::{AppContext, CANVAS_HEIGHT, CANVAS_WIDTH},
 	games::{GAMES, GameInfo},
-	math::{Color, FloatMath, Lerp, Point},
+	math::{Color, FloatMath, Lerp, Point, Rect},
 	painter::{CanvasId, Icon, IconKind, Sprite, Text},
 	util::Anim,
 };
 
 /// Cartridge card sprite
 struct Card {
-	info: &'static GameInfo,
+	info: GameInfo,
 
 	pos: Point,
 	pos_z: f32,
 	selected: bool,
+	/// Sprite rect on the last frame
+	rect: Rect,
 
 	anim: Anim,
 }
 impl Card {
-	fn new(info: &'static GameInfo) -> Self {
+	fn new(info: GameInfo) -> Self {
 		Self {
 			info,
 
 			pos: Point::default(),
 			pos_z: 0.0,
 			selected: false,
+			rect: Rect::new(Point::default(), Point::default()),
 
 			anim: Anim::new(8, 8..16).with_looped().with_playing(),
 		}
@@ -56,6 +61,7 @@ impl Card {
 		sprite.pos.y -= sprite.size.y / 2.0;
 		sprite.pos = sprite.pos.floor();
 		sprite.draw(&mut ctx.painter, canvas);
+		self.rect = sprite.rect();
 
 		// Draw text
 		let mut text = Text::new(&ctx.assets.ibm_font).with_fg(Color::gray(light));
@@ -77,28 +83,33 @@ impl Card {
 
 			Text::new(&ctx.assets.ibm_font)
 				.with_pos((self.pos.x + 150.0, self.pos.y))
-				.draw_str(&mut ctx.painter, canvas, self.info.desc);
+				.draw_str(&mut ctx.painter, canvas, &self.info.desc);
 		}
 	}
 }
 
 /// Cartridge picker
 pub struct CartridgePicker {
+	pub is_open: bool,
+
 	cards: Vec<Card>,
 	sorted_cards: Vec<usize>,
 
 	angle: f32,
 	velocity: f32,
 }
-impl Default for CartridgePicker {
-	fn default() -> Self {
-		let mut cards = Vec::with_capacity(GAMES.len());
+impl CartridgePicker {
+	/// Create a picker with the built-in games followed by `user_games`
+	pub fn new(user_games: Vec<GameInfo>) -> Self {
+		let mut cards = Vec::with_capacity(GAMES.len() + user_games.len());
 
-		for info in GAMES {
+		for info in GAMES.iter().cloned().chain(user_games) {
 			cards.push(Card::new(info))
 		}
 
 		Self {
+			is_open: false,
+
 			sorted_cards: (0..cards.len()).collect(),
 			cards,
 
@@ -106,21 +117,26 @@ impl Default for CartridgePicker {
 			velocity: 0.0,
 		}
 	}
-}
-impl CartridgePicker {
-	pub fn draw(&mut self, ctx: &mut AppContext, canvas: CanvasId) {
+
+	/// Rotate the cards with the mouse
+	/// Returns the picked game, if any, and closes the picker
+	pub fn update(&mut self, ctx: &mut AppContext) -> Option<GameInfo> {
 		const PI: f32 = f32::consts::PI;
-		const PI2: f32 = f32::consts::TAU;
 
-		// Draw darken rect
-		Sprite::new(ctx.painter.white_texture, (CANVAS_WIDTH, CANVAS_HEIGHT))
-			.with_fg((0.0, 0.0, 0.0))
-			.with_opacity(0.8)
-			.draw(&mut ctx.painter, canvas);
+		if ctx.input.key_just_pressed(KeyCode::Escape) {
+			self.is_open = false;
+			return None;
+		}
 
-		let count = self.cards.len();
-		let n = count as f32;
-		let step = PI / (n / 2.0);
+		let step = PI / (self.cards.len() as f32 / 2.0);
+
+		// Pick the selected card by clicking on it or with Enter
+		let card = &self.cards[*self.sorted_cards.last()?];
+		let clicked = ctx.input.left_just_pressed() && card.rect.contains(&ctx.input.mouse_pos);
+		if clicked || ctx.input.key_just_pressed(KeyCode::Enter) {
+			self.is_open = false;
+			return Some(card.info.clone());
+		}
 
 		if ctx.input.left_is_pressed() {
 			self.velocity = -ctx.input.mouse_movement.y / CANVAS_HEIGHT * PI;
@@ -134,7 +150,24 @@ impl CartridgePicker {
 		}
 
 		self.angle += self.velocity;
-		self.angle %= PI2;
+		self.angle %= f32::consts::TAU;
+
+		None
+	}
+
+	pub fn draw(&mut self, ctx: &mut AppContext, canvas: CanvasId) {
+		const PI: f32 = f32::consts::PI;
+		const PI2: f32 = f32::consts::TAU;
+
+		// Draw darken rect
+		Sprite::new(ctx.painter.white_texture, (CANVAS_WIDTH, CANVAS_HEIGHT))
+			.with_fg((0.0, 0.0, 0.0))
+			.with_opacity(0.8)
+			.draw(&mut ctx.painter, canvas);
+
+		let count = self.cards.len();
+		let n = count as f32;
+		let step = PI / (n / 2.0);
 
 		for (i, card) in self.cards.iter_mut().enumerate() {
 			card.pos_z = ((i as f32 / n * PI2 + self.angle + step / 2.0).cos() + 1.0) / 2.0;
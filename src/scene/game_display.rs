@@ -16,10 +16,7 @@ pub struct GameDisplay {
 	speed_text_timer: Timer,
 }
 impl GameDisplay {
-	const SIZE: Point = Point::new(
-		(Emu::SCREEN_WIDTH * 2) as f32,
-		(Emu::SCREEN_HEIGHT * 2) as f32,
-	);
+	const SIZE: Point = Point::new(Emu::SCREEN_WIDTH as f32, Emu::SCREEN_HEIGHT as f32);
 	const BUF_SIZE: usize = Emu::SCREEN_BUF_SIZE * 3;
 	const PROGRESS_WIDTH: usize = 14;
 
@@ -60,24 +57,30 @@ impl GameDisplay {
 		}
 	}
 
-	#[allow(clippy::identity_op)]
-	fn update_texture(&mut self, painter: &Painter, emu: &Emu) {
-		for i in (0..self.buffer.len()).step_by(3) {
-			let buf = &mut self.buffer;
-
-			let min = (255.0 * 0.1) as u8;
-
-			if emu.screen[i / 3] {
-				buf[i + 0] = (255.0 * 0.7) as u8;
-				buf[i + 1] = (255.0 * 0.8) as u8;
-				buf[i + 2] = (255.0 * 0.7) as u8;
-			} else {
-				// Imitate bad display by fading out each pixel on every frame
-				let color = (buf[i] as f32 / 1.5) as u8;
-
-				buf[i + 0] = color.max(min);
-				buf[i + 1] = color.max(min);
-				buf[i + 2] = color.max(min);
+	fn update_texture(&mut self, painter: &Painter, state: &State) {
+		let emu = &state.emu;
+		let palette = state.palette.colors();
+
+		// Texture always has the hi-res size, so low-res pixels are upscaled
+		let scale_x = Emu::SCREEN_WIDTH / emu.screen_width();
+		let scale_y = Emu::SCREEN_HEIGHT / emu.screen_height();
+
+		let min = (255.0 * 0.1) as u8;
+		// Leave longer trails while rewinding
+		let fade = if state.board.rewinding { 1.1 } else { 1.5 };
+
+		for (i, texel) in self.buffer.chunks_mut(3).enumerate() {
+			let x = i % Emu::SCREEN_WIDTH / scale_x;
+			let y = i / Emu::SCREEN_WIDTH / scale_y;
+
+			match emu.pixel(x, y) {
+				0 => {
+					// Imitate bad display by fading out each pixel on every frame
+					for channel in texel.iter_mut() {
+						*channel = ((*channel as f32 / fade) as u8).max(min);
+					}
+				}
+				planes => texel.copy_from_slice(&palette[planes as usize - 1]),
 			}
 		}
 
@@ -91,7 +94,7 @@ impl GameDisplay {
 	}
 
 	pub fn offscreen_draw(&mut self, ctx: &mut AppContext, state: &mut State) {
-		self.update_texture(&ctx.painter, &state.emu);
+		self.update_texture(&ctx.painter, state);
 
 		// Draw game screen
 		Sprite::new(self.texture, Self::SIZE).draw(&mut ctx.painter, self.canvas);
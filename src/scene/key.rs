@@ -3,12 +3,16 @@ use miniquad::KeyCode;
 use crate::{
 	app::AppContext,
 	assets::AssetTexture,
+	gamepad::GamepadButton,
 	painter::{CanvasId, Sprite},
 };
 
 /// Keyboard key sprite
 pub struct Key {
-	pub key_code: Option<KeyCode>,
+	/// Keyboard keys which press the key
+	pub key_codes: Vec<KeyCode>,
+	/// Gamepad buttons which press the key
+	pub buttons: Vec<GamepadButton>,
 	pub pressed: bool,
 	pub just_pressed: bool,
 	pub hovered: bool,
@@ -17,7 +21,8 @@ pub struct Key {
 impl Key {
 	pub fn new(texture: &AssetTexture) -> Self {
 		Self {
-			key_code: None,
+			key_codes: Vec::new(),
+			buttons: Vec::new(),
 			pressed: false,
 			just_pressed: false,
 			hovered: false,
@@ -31,14 +36,12 @@ impl Key {
 
 	pub fn draw(&mut self, ctx: &mut AppContext, canvas: CanvasId) {
 		let input = &mut ctx.input;
-		let (key_is_pressed, key_just_pressed, key_just_released) = match self.key_code {
-			Some(key) => (
-				input.key_is_pressed(key),
-				input.key_just_pressed(key),
-				input.key_just_released(key),
-			),
-			None => (false, false, false),
-		};
+		let key_is_pressed = self.key_codes.iter().any(|k| input.key_is_pressed(*k))
+			|| self.buttons.iter().any(|b| input.button_is_pressed(*b));
+		let key_just_pressed = self.key_codes.iter().any(|k| input.key_just_pressed(*k))
+			|| self.buttons.iter().any(|b| input.button_just_pressed(*b));
+		let key_just_released = self.key_codes.iter().any(|k| input.key_just_released(*k))
+			|| self.buttons.iter().any(|b| input.button_just_released(*b));
 
 		self.hovered = self.sprite.is_hover(input);
 		self.pressed = self.hovered && input.left_is_pressed() || key_is_pressed;
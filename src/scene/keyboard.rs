@@ -1,32 +1,13 @@
-use miniquad::KeyCode;
-
-use crate::{app::AppContext, math::ToStrBytes, painter::CanvasId, scene::key::Key, state::State};
-
-/// Converts CHIP-8 key to [`KeyCode`]
-fn to_key_code(key: u8) -> KeyCode {
-	[
-		KeyCode::X,
-		KeyCode::Key1,
-		KeyCode::Key2,
-		KeyCode::Key3,
-		KeyCode::Q,
-		KeyCode::W,
-		KeyCode::E,
-		KeyCode::A,
-		KeyCode::S,
-		KeyCode::D,
-		KeyCode::Z,
-		KeyCode::C,
-		KeyCode::Key4,
-		KeyCode::R,
-		KeyCode::F,
-		KeyCode::V,
-	][key as usize]
-}
+use crate::{
+	app::AppContext, keymap::Keymap, math::ToStrBytes, movie::MovieTape, painter::CanvasId,
+	scene::key::Key, state::State,
+};
 
 /// Front board keyboard
 #[derive(Default)]
-pub struct Keyboard;
+pub struct Keyboard {
+	pub keymap: Keymap,
+}
 impl Keyboard {
 	#[rustfmt::skip]
 	const LAYOUT: &[i32] = &[
@@ -36,32 +17,6 @@ impl Keyboard {
 		0xa, 0x0, 0xb, 0xf,
 	];
 
-	fn key_code_to_char(key: KeyCode) -> u8 {
-		match key {
-			KeyCode::Key1 => b'1',
-			KeyCode::Key2 => b'2',
-			KeyCode::Key3 => b'3',
-			KeyCode::Key4 => b'4',
-
-			KeyCode::Q => b'Q',
-			KeyCode::W => b'W',
-			KeyCode::E => b'E',
-			KeyCode::R => b'R',
-
-			KeyCode::A => b'A',
-			KeyCode::S => b'S',
-			KeyCode::D => b'D',
-			KeyCode::F => b'F',
-
-			KeyCode::Z => b'Z',
-			KeyCode::X => b'X',
-			KeyCode::C => b'C',
-			KeyCode::V => b'V',
-
-			_ => 0,
-		}
-	}
-
 	pub fn draw(&self, ctx: &mut AppContext, state: &mut State, canvas: CanvasId) {
 		// Keys position is sampled from GIMP
 		const KEYBOARD_X: f32 = 131.0;
@@ -86,23 +41,32 @@ impl Keyboard {
 				sprite.pos.y -= 1.0;
 			}
 
-			let key_code = to_key_code(*key as u8);
+			let keys = &self.keymap.keys[*key as usize];
+			let buttons = &self.keymap.buttons[*key as usize];
 
-			sprite.key_code = Some(key_code);
+			sprite.key_codes.clone_from(keys);
+			sprite.buttons.clone_from(buttons);
 			sprite.set_frame(*key);
 			sprite.draw(ctx, canvas);
 
 			// Show tooltip on hover
 			if sprite.hovered {
-				let mut msg = *b"Key 0x\0 [\0]";
+				let mut msg = b"Key 0x\0".to_vec();
 				msg[6] = key.to_hex_str_bytes(false)[0];
-				msg[9] = Self::key_code_to_char(key_code);
+				if !keys.is_empty() || !buttons.is_empty() {
+					msg.extend_from_slice(
+						format!(" [{}]", self.keymap.key_names(*key as u8)).as_bytes(),
+					);
+				}
 				ctx.tooltip.set(&msg);
 			}
 
-			state
-				.emu
-				.set_pressed_key(*key as u8, sprite.pressed, sprite.just_pressed);
+			// Keys are pressed by the movie during the playback
+			if !state.tape.as_ref().is_some_and(MovieTape::is_playing) {
+				state
+					.emu
+					.set_pressed_key(*key as u8, sprite.pressed, sprite.just_pressed);
+			}
 		}
 	}
 }
@@ -0,0 +1,102 @@
+use crate::{
+	app::AppContext,
+	emu::{
+		Emu,
+		disasm::{self, Line, LineKind},
+	},
+	math::Rect,
+	painter::{CanvasId, Text},
+};
+
+use super::text_panel::TextPanel;
+
+/// Human-readable cartridge view
+/// Disassembly listing that scrolls to follow the program counter
+#[derive(Default)]
+pub struct Listing {
+	lines: Vec<Line>,
+	/// Hash of the disassembled program
+	program_hash: u64,
+}
+impl Listing {
+	const POS: (f32, f32) = (16.0, 16.0);
+	const ROWS: usize = 24;
+	/// Width of each line in chars
+	const COLS: usize = 32;
+
+	/// Click a line to toggle a breakpoint on it
+	pub fn update(&mut self, ctx: &mut AppContext, emu: &mut Emu) {
+		// Disassemble the program again only when it changes
+		let hash = emu.program_hash() ^ emu.mode as u64;
+		if hash != self.program_hash || self.lines.is_empty() {
+			self.lines = disasm::disassemble(&emu.program, emu.mode);
+			self.program_hash = hash;
+		}
+
+		let char_size = Text::new(&ctx.assets.ibm_font).char_size();
+		let (_, first) = self.visible_lines(emu.pc);
+
+		for row in 0..Self::ROWS {
+			let Some(line) = self.lines.get(first + row) else {
+				break;
+			};
+
+			let rect = Rect::new_xywh(
+				Self::POS.0,
+				Self::POS.1 + char_size.y * row as f32,
+				char_size.x * Self::COLS as f32,
+				char_size.y,
+			);
+			if line.kind == LineKind::Code && rect.is_hover(&mut ctx.input) {
+				ctx.tooltip.set(b"Toggle breakpoint");
+
+				if ctx.input.left_just_pressed() {
+					emu.debugger.toggle_breakpoint(line.addr);
+				}
+			}
+		}
+	}
+
+	/// Index of the current instruction line and of the first visible line
+	/// The current instruction is kept at the listing center
+	fn visible_lines(&self, pc: u16) -> (usize, usize) {
+		let cur = self
+			.lines
+			.partition_point(|line| line.addr + line.len <= pc);
+		let first = cur
+			.saturating_sub(Self::ROWS / 2)
+			.min(self.lines.len().saturating_sub(Self::ROWS));
+
+		(cur, first)
+	}
+
+	pub fn draw(&self, ctx: &mut AppContext, emu: &Emu, canvas: CanvasId) {
+		let (cur, first) = self.visible_lines(emu.pc);
+
+		let mut panel = TextPanel::new(&ctx.assets.ibm_font, Self::POS, Self::COLS);
+
+		for row in 0..Self::ROWS {
+			let line = self.lines.get(first + row);
+
+			let chars = match line {
+				Some(line) => {
+					// Lines with a breakpoint are marked with `*`
+					let mark = if emu.debugger.has_breakpoint(line.addr) {
+						'*'
+					} else {
+						' '
+					};
+					format!("{:04X}{mark}{}", line.addr, line.text)
+				}
+				None => String::new(),
+			};
+
+			let fg = match line {
+				Some(line) if line.kind != LineKind::Code => TextPanel::DIM_FG,
+				_ => TextPanel::FG,
+			};
+			// Highlight the current instruction
+			panel.draw_line(&mut ctx.painter, canvas, chars, fg, first + row == cur);
+		}
+	}
+}
@@ -0,0 +1,137 @@
+use crate::{
+	app::{AppContext, CANVAS_WIDTH},
+	emu::Emu,
+	math::Rect,
+	painter::{CanvasId, Text},
+	state::State,
+};
+
+use super::text_panel::TextPanel;
+
+/// Per-frame input of the movie being recorded
+/// Each row is a frame and each column is a CHIP-8 key
+pub struct PianoRoll;
+impl PianoRoll {
+	/// Chars of the IBM font are 8 pixels wide
+	const POS: (f32, f32) = (CANVAS_WIDTH - 16.0 - 8.0 * Self::COLS as f32, 16.0);
+	/// Number of frame rows, the header row is not counted
+	const ROWS: usize = 23;
+	/// Width of the frame number in chars
+	const FRAME_COLS: usize = 7;
+	/// Width of each line in chars
+	const COLS: usize = Self::FRAME_COLS + Emu::KEYS_COUNT;
+
+	/// Click a frame number to play the movie up to it, click a key to toggle it on the frame
+	pub fn update(&mut self, ctx: &mut AppContext, state: &mut State) {
+		let Some(tape) = &mut state.tape else {
+			return;
+		};
+
+		let char_size = Text::new(&ctx.assets.ibm_font).char_size();
+		let first = Self::first_frame(tape.pos(), tape.movie.frames.len());
+
+		for row in 0..Self::ROWS {
+			let frame = first + row;
+			if frame >= tape.movie.frames.len() {
+				break;
+			}
+
+			let y = Self::POS.1 + char_size.y * (row + 1) as f32;
+			let frame_rect = Rect::new_xywh(
+				Self::POS.0,
+				y,
+				char_size.x * Self::FRAME_COLS as f32,
+				char_size.y,
+			);
+			if frame_rect.is_hover(&mut ctx.input) {
+				ctx.tooltip.set(format!("Seek to frame {frame}").as_bytes());
+
+				if ctx.input.left_just_pressed()
+					&& let Err(e) = tape.seek(&mut state.emu, frame)
+				{
+					ctx.tooltip.set_error(e.to_string().as_bytes());
+				}
+			}
+
+			for key in 0..Emu::KEYS_COUNT as u8 {
+				let key_rect = Rect::new_xywh(
+					Self::POS.0 + char_size.x * (Self::FRAME_COLS + key as usize) as f32,
+					y,
+					char_size.x,
+					char_size.y,
+				);
+				if !key_rect.is_hover(&mut ctx.input) {
+					continue;
+				}
+
+				ctx.tooltip
+					.set(format!("Toggle key 0x{key:X} on frame {frame}").as_bytes());
+
+				if ctx.input.left_just_pressed()
+					&& let Err(e) = tape.toggle_key(&mut state.emu, frame, key)
+				{
+					ctx.tooltip.set_error(e.to_string().as_bytes());
+				}
+			}
+		}
+	}
+
+	/// First visible frame, the upcoming frame is kept at the roll center
+	fn first_frame(pos: usize, len: usize) -> usize {
+		pos.saturating_sub(Self::ROWS / 2)
+			.min(len.saturating_sub(Self::ROWS - Self::ROWS / 2))
+	}
+
+	pub fn draw(&self, ctx: &mut AppContext, state: &State, canvas: CanvasId) {
+		let Some(tape) = &state.tape else {
+			return;
+		};
+
+		let mut panel = TextPanel::new(&ctx.assets.ibm_font, Self::POS, Self::COLS);
+
+		let mut header = format!("{:<1$}", "FRAME", Self::FRAME_COLS);
+		header.extend((0..Emu::KEYS_COUNT).map(|key| char::from_digit(key as u32, 16).unwrap()));
+		panel.draw_line(
+			&mut ctx.painter,
+			canvas,
+			header.to_uppercase(),
+			TextPanel::FG,
+			false,
+		);
+
+		let pos = tape.pos();
+		let first = Self::first_frame(pos, tape.movie.frames.len());
+
+		for row in 0..Self::ROWS {
+			let frame = first + row;
+
+			let chars = match tape.movie.frames.get(frame) {
+				Some(input) => {
+					let mut line = format!("{frame:<width$}", width = Self::FRAME_COLS);
+					line.extend((0..Emu::KEYS_COUNT as u8).map(|key| {
+						if input.is_key_held(key) {
+							char::from_digit(key as u32, 16)
+								.unwrap()
+								.to_ascii_uppercase()
+						} else {
+							'.'
+						}
+					}));
+					line
+				}
+				// The upcoming frame is recorded from the keyboard
+				None if frame == pos => format!("{frame:<width$}REC", width = Self::FRAME_COLS),
+				None => String::new(),
+			};
+
+			// Frames which are not played yet are dimmed
+			let fg = if frame > pos {
+				TextPanel::DIM_FG
+			} else {
+				TextPanel::FG
+			};
+			// Highlight the upcoming frame
+			panel.draw_line(&mut ctx.painter, canvas, chars, fg, frame == pos);
+		}
+	}
+}
@@ -0,0 +1,119 @@
+use crate::{
+	app::{AppContext, find_game},
+	emu::Emu,
+	math::Rect,
+	painter::{CanvasId, Text},
+	state::State,
+};
+
+use super::text_panel::TextPanel;
+
+/// Setting row of the panel
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Row {
+	Sound,
+	Speed,
+	Side,
+	Rom,
+}
+impl Row {
+	const ALL: [Self; 4] = [Self::Sound, Self::Speed, Self::Side, Self::Rom];
+
+	fn name(self) -> &'static str {
+		match self {
+			Self::Sound => "SOUND",
+			Self::Speed => "SPEED",
+			Self::Side => "SIDE",
+			Self::Rom => "ROM",
+		}
+	}
+	fn tooltip(self) -> &'static [u8] {
+		match self {
+			Self::Sound => b"Toggle sound",
+			Self::Speed => b"Reset speed, change it with the valve",
+			Self::Side => b"Flip the board",
+			Self::Rom => b"Pick another cartridge",
+		}
+	}
+}
+
+/// Click on a setting which has to be handled by the scene
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SettingsAction {
+	FlipBoard,
+	OpenPicker,
+}
+
+/// Settings which are saved between restarts
+pub struct SettingsPanel;
+impl SettingsPanel {
+	const POS: (f32, f32) = (16.0, 16.0);
+	/// Width of the setting name in chars
+	const NAME_COLS: usize = 7;
+	/// Width of each line in chars
+	const COLS: usize = 32;
+
+	/// Click a setting to change it
+	pub fn update(&mut self, ctx: &mut AppContext, state: &mut State) -> Option<SettingsAction> {
+		let char_size = Text::new(&ctx.assets.ibm_font).char_size();
+		let mut action = None;
+
+		for (i, row) in Row::ALL.into_iter().enumerate() {
+			let rect = Rect::new_xywh(
+				Self::POS.0,
+				Self::POS.1 + char_size.y * (i + 1) as f32,
+				char_size.x * Self::COLS as f32,
+				char_size.y,
+			);
+			if !rect.is_hover(&mut ctx.input) {
+				continue;
+			}
+
+			ctx.tooltip.set(row.tooltip());
+			if !ctx.input.left_just_pressed() {
+				continue;
+			}
+
+			match row {
+				Row::Sound => {
+					let muted = !ctx.audio.is_muted();
+					ctx.audio.set_muted(muted);
+					if ctx.audio.is_muted() != muted {
+						ctx.tooltip.set_error(b"No audio output");
+					}
+				}
+				Row::Speed => state.emu.set_speed(Emu::DEFAULT_SPEED),
+				Row::Side => action = Some(SettingsAction::FlipBoard),
+				Row::Rom => action = Some(SettingsAction::OpenPicker),
+			}
+		}
+
+		action
+	}
+
+	fn value(row: Row, ctx: &AppContext, state: &State) -> String {
+		match row {
+			Row::Sound if ctx.audio.is_muted() => "OFF".to_string(),
+			Row::Sound => "ON".to_string(),
+			Row::Speed => format!("{:.2}x", state.emu.speed),
+			Row::Side => state.board.side.name().to_uppercase(),
+			Row::Rom => match &state.rom_path {
+				Some(path) => path.file_name().unwrap_or_default().display().to_string(),
+				None => find_game(&state.emu.program)
+					.map(|game| game.title.to_string())
+					.unwrap_or_default(),
+			},
+		}
+	}
+
+	pub fn draw(&self, ctx: &mut AppContext, state: &State, canvas: CanvasId) {
+		let mut panel = TextPanel::new(&ctx.assets.ibm_font, Self::POS, Self::COLS);
+		panel.draw_line(&mut ctx.painter, canvas, "SETTINGS", TextPanel::FG, false);
+
+		for row in Row::ALL {
+			let value = Self::value(row, ctx, state);
+			let line = format!("{:<width$}{value}", row.name(), width = Self::NAME_COLS);
+			panel.draw_line(&mut ctx.painter, canvas, line, TextPanel::FG, false);
+		}
+	}
+}
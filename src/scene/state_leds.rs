@@ -11,12 +11,15 @@ use crate::{
 pub struct StateLeds {
 	show_error_timer: Timer,
 	show_used_timer: Timer,
+	/// Number of emulator errors the LED has already blinked for
+	errors_count: u32,
 }
 impl Default for StateLeds {
 	fn default() -> Self {
 		Self {
 			show_error_timer: Timer::from_millis(600),
 			show_used_timer: Timer::from_millis(200),
+			errors_count: 0,
 		}
 	}
 }
@@ -25,10 +28,11 @@ impl StateLeds {
 		self.show_error_timer.update(&ctx.time);
 		self.show_used_timer.update(&ctx.time);
 
-		if state.emu.error {
+		// The count drops back to zero on reset
+		if state.emu.errors.count > self.errors_count {
 			self.show_error_timer.start();
-			state.emu.error = false;
 		}
+		self.errors_count = state.emu.errors.count;
 		if state.emu.key_checked {
 			self.show_used_timer.start();
 			state.emu.key_checked = false
@@ -65,10 +69,18 @@ impl StateLeds {
 		sprite.frame.x = 2;
 		sprite.draw(&mut ctx.painter, canvas);
 
-		if !self.show_error_timer.finished() && ctx.time.elapsed % 10 < 5 {
-			// Error LED
-			sprite.pos = ERROR_POS;
-			sprite.frame.x = 3;
+		// Error LED, stays lit while halted at a breakpoint
+		sprite.pos = ERROR_POS;
+		sprite.frame.x = 3;
+
+		if sprite.is_hover(&mut ctx.input)
+			&& let Some(e) = state.emu.errors.last()
+		{
+			ctx.tooltip.set(e.to_string().as_bytes());
+		}
+
+		let blink = !self.show_error_timer.finished() && ctx.time.elapsed % 10 < 5;
+		if blink || state.emu.debugger.hit.is_some() {
 			sprite.draw(&mut ctx.painter, canvas);
 		}
 	}
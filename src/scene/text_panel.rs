@@ -0,0 +1,45 @@
+use crate::{
+	math::Color,
+	painter::{CanvasId, Font, Painter, Text},
+};
+
+/// Fixed width text lines over the board, e.g. the listing or the settings
+pub struct TextPanel<'a> {
+	text: Text<'a>,
+	/// Width of each line in chars
+	cols: usize,
+}
+impl<'a> TextPanel<'a> {
+	pub const FG: Color = Color::new(0.7, 0.8, 0.7);
+	/// Foreground of less important lines
+	pub const DIM_FG: Color = Color::new(0.35, 0.5, 0.4);
+	pub const BG: Color = Color::gray(0.1);
+
+	pub fn new(font: &'a Font, pos: (f32, f32), cols: usize) -> Self {
+		Self {
+			text: Text::new(font).with_pos(pos),
+			cols,
+		}
+	}
+
+	/// Draw the line padded or cut to the panel width
+	/// Highlighted line has inverted colors
+	pub fn draw_line(
+		&mut self,
+		painter: &mut Painter,
+		canvas: CanvasId,
+		line: impl Into<Vec<u8>>,
+		fg: Color,
+		highlight: bool,
+	) {
+		let mut chars = line.into();
+		chars.resize(self.cols, b' ');
+
+		(self.text.foreground, self.text.background) = if highlight {
+			(Self::BG, fg)
+		} else {
+			(fg, Self::BG)
+		};
+		self.text.draw_line(painter, canvas, &chars);
+	}
+}
@@ -0,0 +1,228 @@
+use std::path::PathBuf;
+
+use crate::{
+	app::find_game,
+	audio::Audio,
+	config::{ConfigError, parse_config},
+	emu::Emu,
+	state::{BoardSide, State},
+};
+
+/// Settings which survive restarts
+///
+/// Settings are stored as `key = value` lines, e.g. `speed = 1.5`, in the config directory on
+/// native builds and in the browser local storage on the web.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Settings {
+	pub muted: bool,
+	/// Execution speed set with the valve
+	pub speed: f32,
+	pub side: BoardSide,
+	/// Last played ROM file
+	pub rom: Option<PathBuf>,
+	/// Title of the last played built-in game
+	pub game: Option<String>,
+}
+impl Default for Settings {
+	fn default() -> Self {
+		Self {
+			muted: false,
+			speed: Emu::DEFAULT_SPEED,
+			side: BoardSide::Front,
+			rom: None,
+			game: None,
+		}
+	}
+}
+impl Settings {
+	/// Name of the settings file in the config directory
+	pub const FILE_NAME: &str = "settings.conf";
+	/// Key of the settings item in the browser local storage
+	#[cfg(target_arch = "wasm32")]
+	const STORAGE_KEY: &str = "chip-maximator/settings.conf";
+
+	/// Settings of the running app
+	pub fn current(state: &State, audio: &Audio) -> Self {
+		Self {
+			muted: audio.is_muted(),
+			speed: state.emu.speed,
+			side: state.board.side,
+			rom: state.rom_path.clone(),
+			game: match state.rom_path {
+				Some(_) => None,
+				None => find_game(&state.emu.program).map(|game| game.title.to_string()),
+			},
+		}
+	}
+
+	/// Override settings listed in the settings file source
+	pub fn apply(&mut self, source: &str) -> Result<(), ConfigError> {
+		let parse_bool = |value: &str| match value {
+			"true" | "on" | "yes" => Some(true),
+			"false" | "off" | "no" => Some(false),
+			_ => None,
+		};
+		let non_empty = |value: &str| (!value.is_empty()).then(|| value.to_string());
+
+		for entry in parse_config(source)? {
+			let value = entry.value;
+			let invalid = || entry.error(format!("invalid {} \"{value}\"", entry.key));
+
+			match entry.key {
+				"muted" => self.muted = parse_bool(value).ok_or_else(invalid)?,
+				"speed" => {
+					self.speed = value
+						.parse::<f32>()
+						.ok()
+						.filter(|speed| (Emu::MIN_SPEED..=Emu::MAX_SPEED).contains(speed))
+						.ok_or_else(invalid)?
+				}
+				"side" => self.side = BoardSide::from_name(value).ok_or_else(invalid)?,
+				"rom" => self.rom = non_empty(value).map(PathBuf::from),
+				"game" => self.game = non_empty(value),
+				key => return Err(entry.error(format!("unknown setting \"{key}\""))),
+			}
+		}
+
+		Ok(())
+	}
+
+	/// Settings file source
+	pub fn to_config(&self) -> String {
+		let mut source =
+			String::from("# CHIP MAXIMATOR settings, keys are mapped in keymap.conf\n");
+		source += &format!("muted = {}\n", self.muted);
+		source += &format!("speed = {}\n", self.speed);
+		source += &format!("side = {}\n", self.side.name());
+		if let Some(rom) = &self.rom {
+			source += &format!("rom = {}\n", rom.display());
+		}
+		if let Some(game) = &self.game {
+			source += &format!("game = {game}\n");
+		}
+		source
+	}
+
+	/// Path of the settings file
+	#[cfg(not(target_arch = "wasm32"))]
+	pub fn path() -> Option<PathBuf> {
+		crate::config::config_dir().map(|dir| dir.join(Self::FILE_NAME))
+	}
+
+	/// Default settings with the saved ones applied
+	/// An invalid settings file is reported and the settings after the error are skipped
+	pub fn load() -> Self {
+		let mut settings = Self::default();
+
+		#[cfg(not(target_arch = "wasm32"))]
+		if let Some(path) = Self::path()
+			&& let Ok(source) = std::fs::read_to_string(&path)
+			&& let Err(e) = settings.apply(&source)
+		{
+			eprintln!("ERROR: {}: {e}", path.display());
+		}
+
+		#[cfg(target_arch = "wasm32")]
+		if let Some(source) = crate::native::storage_get(Self::STORAGE_KEY)
+			&& let Err(e) = settings.apply(&source)
+		{
+			eprintln!("ERROR: {}: {e}", Self::STORAGE_KEY);
+		}
+
+		settings
+	}
+	#[cfg(not(target_arch = "wasm32"))]
+	pub fn save(&self) {
+		let Some(path) = Self::path() else {
+			eprintln!("ERROR: failed to save settings: no config directory");
+			return;
+		};
+
+		let res = match path.parent() {
+			Some(dir) => std::fs::create_dir_all(dir),
+			None => Ok(()),
+		}
+		.and_then(|_| std::fs::write(&path, self.to_config()));
+		if let Err(e) = res {
+			eprintln!("ERROR: failed to save settings to {}: {e}", path.display());
+		}
+	}
+	#[cfg(target_arch = "wasm32")]
+	pub fn save(&self) {
+		crate::native::storage_set(Self::STORAGE_KEY, &self.to_config());
+	}
+}
+
+/// Saved settings, kept in sync with the ones changed in the app
+/// Settings overridden with the CLI are not saved until they are changed in the app
+pub struct SettingsStore {
+	pub saved: Settings,
+	/// App settings on the last update
+	last: Settings,
+}
+impl SettingsStore {
+	pub fn new(saved: Settings, state: &State, audio: &Audio) -> Self {
+		Self {
+			saved,
+			last: Settings::current(state, audio),
+		}
+	}
+
+	/// Save the settings changed in the app since the last update
+	pub fn update(&mut self, state: &State, audio: &Audio) {
+		// Don't save every step of the valve rotation
+		if state.valve.is_rotating {
+			return;
+		}
+
+		let cur = Settings::current(state, audio);
+		if cur == self.last {
+			return;
+		}
+
+		if cur.muted != self.last.muted {
+			self.saved.muted = cur.muted;
+		}
+		if cur.speed != self.last.speed {
+			self.saved.speed = cur.speed;
+		}
+		if cur.side != self.last.side {
+			self.saved.side = cur.side;
+		}
+		if cur.rom != self.last.rom || cur.game != self.last.game {
+			self.saved.rom = cur.rom.clone();
+			self.saved.game = cur.game.clone();
+		}
+
+		self.saved.save();
+		self.last = cur;
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn apply() {
+		let mut settings = Settings::default();
+		settings
+			.apply("muted = on\nspeed = 1.5\nside = back\nrom = /tmp/game.ch8\n")
+			.unwrap();
+
+		assert!(settings.muted);
+		assert_eq!(settings.speed, 1.5);
+		assert_eq!(settings.side, BoardSide::Back);
+		assert_eq!(settings.rom, Some(PathBuf::from("/tmp/game.ch8")));
+
+		let mut loaded = Settings::default();
+		loaded.apply(&settings.to_config()).unwrap();
+		assert_eq!(loaded, settings);
+
+		let e = settings.apply("muted = false\nside = top").unwrap_err();
+		assert_eq!(e.to_string(), "line 2: invalid side \"top\"");
+		assert!(!settings.muted);
+		assert!(settings.apply("speed = 100").is_err());
+		assert!(settings.apply("volume = 1").is_err());
+	}
+}
@@ -1,6 +1,10 @@
+use std::path::PathBuf;
+
 use crate::{
 	app::AppContext,
 	emu::Emu,
+	movie::MovieTape,
+	palette::Palette,
 	util::{Tweenable, TweenableRef},
 };
 
@@ -10,6 +14,21 @@ pub enum BoardSide {
 	Front,
 	Back,
 }
+impl BoardSide {
+	pub fn from_name(name: &str) -> Option<Self> {
+		match name {
+			"front" => Some(Self::Front),
+			"back" => Some(Self::Back),
+			_ => None,
+		}
+	}
+	pub fn name(self) -> &'static str {
+		match self {
+			Self::Front => "front",
+			Self::Back => "back",
+		}
+	}
+}
 
 /// State
 pub struct State {
@@ -17,6 +36,14 @@ pub struct State {
 	pub board: BoardState,
 	pub valve: ValveState,
 	pub leds: InstuctionLedsState,
+	/// Path of the loaded ROM file, `None` for built-in ROMs
+	pub rom_path: Option<PathBuf>,
+	/// Movie being recorded or played
+	pub tape: Option<MovieTape>,
+	/// Colors of the game display
+	pub palette: Palette,
+	/// Whether the CPU heats up and can explode
+	pub overheat: bool,
 }
 impl State {
 	pub fn new() -> Self {
@@ -25,6 +52,10 @@ impl State {
 			board: BoardState::default(),
 			valve: ValveState::default(),
 			leds: InstuctionLedsState::default(),
+			rom_path: None,
+			tape: None,
+			palette: Palette::default(),
+			overheat: true,
 		}
 	}
 
@@ -32,11 +63,10 @@ impl State {
 		// A cool sword
 		// ===):::::::::::::::>
 
-		self.emu = Emu {
-			program: self.emu.program,
-			..Default::default()
-		};
 		self.emu.setup();
+		// Unlike the emulator reset, the board reset also cools down the CPU and resets the valve
+		self.emu.speed = Emu::DEFAULT_SPEED;
+		self.emu.heat = 0.0;
 
 		self.board = Default::default();
 		self.leds = Default::default();
@@ -50,12 +80,15 @@ pub struct BoardState {
 	/// Whether the board is currently turned on
 	pub power: bool,
 	pub side: BoardSide,
+	/// Whether the emulator is currently being rewound
+	pub rewinding: bool,
 }
 impl Default for BoardState {
 	fn default() -> Self {
 		Self {
 			power: false,
 			side: BoardSide::Front,
+			rewinding: false,
 		}
 	}
 }
@@ -0,0 +1,10 @@
+/// 64-bit FNV-1a hash
+/// Simple and stable between builds, so it can be stored in files
+pub fn fnv1a(bytes: &[u8]) -> u64 {
+	const OFFSET: u64 = 0xcbf29ce484222325;
+	const PRIME: u64 = 0x100000001b3;
+
+	bytes.iter().fold(OFFSET, |hash, byte| {
+		(hash ^ *byte as u64).wrapping_mul(PRIME)
+	})
+}
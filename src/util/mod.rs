@@ -1,9 +1,11 @@
 mod anim;
+mod hash;
 mod timeline;
 mod timer;
 mod tweenable;
 
 pub use anim::*;
+pub use hash::*;
 pub use timeline::*;
 pub use timer::*;
 pub use tweenable::*;
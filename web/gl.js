@@ -638,6 +638,40 @@ var importObject = {
             console.log("Open url", url);
             return window.open(url, "_blank");
         },
+        gamepad_buttons: function() {
+            // Buttons pressed on any of the gamepads, bits are in the standard layout order
+            var mask = 0;
+            var gamepads = navigator.getGamepads ? navigator.getGamepads() : [];
+            for (var i = 0; i < gamepads.length; i++) {
+                var gamepad = gamepads[i];
+                if (!gamepad) {
+                    continue;
+                }
+                for (var b = 0; b < 16 && b < gamepad.buttons.length; b++) {
+                    if (gamepad.buttons[b].pressed) {
+                        mask |= 1 << b;
+                    }
+                }
+            }
+            return mask;
+        },
+        storage_len: function(key, key_len) {
+            // Size of the local storage item in UTF-8 bytes, -1 if there is no such item
+            var value = localStorage.getItem(UTF8ToString(key, key_len));
+            return value == null ? -1 : new TextEncoder().encode(value).length;
+        },
+        storage_read: function(key, key_len, ptr, max_length) {
+            var value = localStorage.getItem(UTF8ToString(key, key_len)) || "";
+            var bytes = new TextEncoder().encode(value).subarray(0, max_length);
+            new Uint8Array(wasm_memory.buffer, ptr, max_length).set(bytes);
+        },
+        storage_write: function(key, key_len, value, value_len) {
+            try {
+                localStorage.setItem(UTF8ToString(key, key_len), UTF8ToString(value, value_len));
+            } catch (e) {
+                console.error("Failed to write the local storage", e);
+            }
+        },
 
         console_debug: function (ptr) {
             console.debug(UTF8ToString(ptr));